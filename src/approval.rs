@@ -0,0 +1,381 @@
+use crate::error::ContractError;
+use crate::event::{GenericEvent, NearEvent, NftApproveData, NftRevokeAllData, NftRevokeData};
+use crate::Contract;
+use near_contract_standards::non_fungible_token::approval::NonFungibleTokenApproval;
+use near_contract_standards::non_fungible_token::TokenId;
+use near_sdk::json_types::U64;
+use near_sdk::serde_json::json;
+use near_sdk::{env, ext_contract, near_bindgen, require, AccountId, FunctionError, Gas, Promise};
+
+const GAS_FOR_NFT_APPROVE: Gas = Gas(10_000_000_000_000);
+
+/// NEP-178 has no operators, so their events are reported under a standard of their own.
+pub(crate) const OPERATOR_EVENT_STANDARD: &str = "nft_operator";
+pub(crate) const OPERATOR_EVENT_VERSION: &str = "1.0.0";
+
+#[ext_contract(ext_approval_receiver)]
+pub trait NonFungibleTokenApprovalReceiver {
+    fn nft_on_approve(
+        &mut self,
+        token_id: TokenId,
+        owner_id: AccountId,
+        approval_id: u64,
+        msg: String,
+    );
+}
+
+#[near_bindgen]
+impl NonFungibleTokenApproval for Contract {
+    #[payable]
+    fn nft_approve(
+        &mut self,
+        token_id: TokenId,
+        account_id: AccountId,
+        msg: Option<String>,
+    ) -> Option<Promise> {
+        crate::assert_valid_token_id(&token_id);
+        // The standard increments the id unchecked, so fail before it would run out
+        if let Some(next_approval_id_by_id) = &self.tokens.next_approval_id_by_id {
+            require!(
+                next_approval_id_by_id.get(&token_id) != Some(u64::MAX),
+                ContractError::ApprovalIdOverflow.message()
+            );
+        }
+        let promise = self
+            .tokens
+            .nft_approve(token_id.clone(), account_id.clone(), msg);
+        // A new approval never inherits the expiry of a previous one
+        self.approval_expiries
+            .remove(&(token_id.clone(), account_id.clone()));
+
+        // Create a NearEvent
+        let owner_id = env::predecessor_account_id();
+        let approval_id = self
+            .current_approval_id(&token_id, &account_id)
+            .unwrap_or_else(|| ContractError::ApprovalNotFound.panic());
+        self.emit_event(NearEvent::nft_approve(vec![NftApproveData::new(
+            &owner_id,
+            &token_id,
+            &account_id,
+            approval_id,
+        )]));
+        promise
+    }
+
+    #[payable]
+    fn nft_revoke(&mut self, token_id: TokenId, account_id: AccountId) {
+        let was_approved = self.current_approval_id(&token_id, &account_id).is_some();
+        self.tokens.nft_revoke(token_id.clone(), account_id.clone());
+        self.remove_approval_expiries(&token_id, [&account_id]);
+        if was_approved {
+            let owner_id = self.tokens.owner_by_id.get(&token_id).unwrap();
+            self.emit_event(NearEvent::nft_revoke(vec![NftRevokeData::new(
+                &owner_id,
+                &token_id,
+                &account_id,
+            )]));
+        }
+    }
+
+    #[payable]
+    fn nft_revoke_all(&mut self, token_id: TokenId) {
+        let approvals = self
+            .tokens
+            .approvals_by_id
+            .as_ref()
+            .and_then(|by_id| by_id.get(&token_id))
+            .unwrap_or_default();
+        self.tokens.nft_revoke_all(token_id.clone());
+        self.remove_approval_expiries(&token_id, approvals.keys());
+        if !approvals.is_empty() {
+            let owner_id = self.tokens.owner_by_id.get(&token_id).unwrap();
+            self.emit_event(NearEvent::nft_revoke_all(vec![NftRevokeAllData::new(
+                &owner_id, &token_id,
+            )]));
+        }
+    }
+
+    /// Whether `approved_account_id` is approved on `token_id` itself. Operators set with
+    /// `set_approval_for_all` aren't per-token approvals and aren't reported here, see
+    /// `is_approved_for_all`.
+    fn nft_is_approved(
+        &self,
+        token_id: TokenId,
+        approved_account_id: AccountId,
+        approval_id: Option<u64>,
+    ) -> bool {
+        !self.is_approval_expired(&token_id, &approved_account_id)
+            && self
+                .tokens
+                .nft_is_approved(token_id, approved_account_id, approval_id)
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Approves `account_id` like `nft_approve`, until the block timestamp reaches
+    /// `expires_at_ns`. The attached deposit must also cover the storage of the expiry.
+    #[payable]
+    pub fn approve_with_expiry(
+        &mut self,
+        token_id: TokenId,
+        account_id: AccountId,
+        msg: Option<String>,
+        expires_at_ns: U64,
+    ) -> Option<Promise> {
+        let expires_at_ns = expires_at_ns.0;
+        require!(
+            env::attached_deposit() > 0,
+            ContractError::DepositRequired.message()
+        );
+        require!(
+            expires_at_ns > env::block_timestamp(),
+            ContractError::ExpirationInPast.message()
+        );
+        let owner_id = env::predecessor_account_id();
+        let initial_storage_usage = env::storage_usage();
+
+        let approval_id = self.internal_approve(&owner_id, &token_id, &account_id);
+        self.approval_expiries.insert(
+            &(token_id.clone(), account_id.clone()),
+            &(approval_id, expires_at_ns),
+        );
+
+        // Charge the storage of the approval and its expiry and return the rest of the deposit
+        self.refund_deposit(env::storage_usage() - initial_storage_usage);
+
+        // Create a NearEvent
+        self.emit_event(NearEvent::nft_approve(vec![NftApproveData::new(
+            &owner_id,
+            &token_id,
+            &account_id,
+            approval_id,
+        )]));
+
+        msg.map(|msg| {
+            ext_approval_receiver::nft_on_approve(
+                token_id,
+                owner_id,
+                approval_id,
+                msg,
+                account_id,
+                crate::NO_DEPOSIT,
+                env::prepaid_gas() - GAS_FOR_NFT_APPROVE,
+            )
+        })
+    }
+
+    /// Approves `account_id` on every token of `token_ids`, which the caller must all own, and
+    /// emits a single approve event. Returns the approval ids in the order of `token_ids`.
+    /// With a `msg`, `nft_on_approve` is called on `account_id` for each token, sharing the
+    /// remaining gas.
+    #[payable]
+    pub fn nft_approve_many(
+        &mut self,
+        token_ids: Vec<TokenId>,
+        account_id: AccountId,
+        msg: Option<String>,
+    ) -> Vec<u64> {
+        require!(
+            env::attached_deposit() > 0,
+            ContractError::DepositRequired.message()
+        );
+        require!(!token_ids.is_empty(), "token_ids must not be empty");
+        let owner_id = env::predecessor_account_id();
+        let initial_storage_usage = env::storage_usage();
+
+        let approval_ids: Vec<u64> = token_ids
+            .iter()
+            .map(|token_id| self.internal_approve(&owner_id, token_id, &account_id))
+            .collect();
+
+        // Charge the storage of the new approvals and return the rest of the deposit
+        self.refund_deposit(env::storage_usage() - initial_storage_usage);
+
+        // Create a NearEvent
+        self.emit_event(NearEvent::nft_approve(
+            token_ids
+                .iter()
+                .zip(&approval_ids)
+                .map(|(token_id, approval_id)| {
+                    NftApproveData::new(&owner_id, token_id, &account_id, *approval_id)
+                })
+                .collect(),
+        ));
+
+        if let Some(msg) = msg {
+            require!(
+                env::prepaid_gas() > env::used_gas() + GAS_FOR_NFT_APPROVE,
+                ContractError::MoreGasRequired.message()
+            );
+            let gas_per_call = Gas(
+                (env::prepaid_gas() - env::used_gas() - GAS_FOR_NFT_APPROVE).0
+                    / token_ids.len() as u64,
+            );
+            for (token_id, approval_id) in token_ids.into_iter().zip(approval_ids.clone()) {
+                ext_approval_receiver::nft_on_approve(
+                    token_id,
+                    owner_id.clone(),
+                    approval_id,
+                    msg.clone(),
+                    account_id.clone(),
+                    crate::NO_DEPOSIT,
+                    gas_per_call,
+                );
+            }
+        }
+        approval_ids
+    }
+
+    /// Allows or disallows `operator_id` to transfer any token of the caller, current or
+    /// future, and emits an `approval_for_all` event. Like `nft_approve`, it requires a deposit
+    /// covering the storage it adds.
+    #[payable]
+    pub fn set_approval_for_all(&mut self, operator_id: AccountId, approved: bool) {
+        require!(
+            env::attached_deposit() > 0,
+            ContractError::DepositRequired.message()
+        );
+        let owner_id = env::predecessor_account_id();
+        require!(
+            owner_id != operator_id,
+            ContractError::SelfApproval.message()
+        );
+        let initial_storage_usage = env::storage_usage();
+        let key = (owner_id.clone(), operator_id.clone());
+        if approved {
+            self.operator_approvals.insert(&key, &true);
+        } else {
+            self.operator_approvals.remove(&key);
+        }
+
+        let storage_usage = env::storage_usage();
+        self.refund_deposit(storage_usage.saturating_sub(initial_storage_usage));
+        crate::refund_storage(
+            &owner_id,
+            initial_storage_usage.saturating_sub(storage_usage),
+        );
+
+        self.emit_generic_event(GenericEvent::new(
+            OPERATOR_EVENT_STANDARD,
+            OPERATOR_EVENT_VERSION,
+            "approval_for_all",
+            json!([{ "owner_id": owner_id, "operator_id": operator_id, "approved": approved }]),
+        ));
+    }
+
+    /// Whether `operator_id` may transfer any token of `owner_id`.
+    pub fn is_approved_for_all(&self, owner_id: AccountId, operator_id: AccountId) -> bool {
+        self.operator_approvals
+            .contains_key(&(owner_id, operator_id))
+    }
+
+    /// Returns the approval id of `approved_account_id` on `token_id`, or `None` if the token
+    /// doesn't exist, the account isn't approved or its approval has expired.
+    pub fn nft_approval_id(
+        &self,
+        token_id: TokenId,
+        approved_account_id: AccountId,
+    ) -> Option<u64> {
+        if self.is_approval_expired(&token_id, &approved_account_id) {
+            return None;
+        }
+        self.current_approval_id(&token_id, &approved_account_id)
+    }
+}
+
+/// Returns the approval id following `approval_id`, panicking rather than reusing ids once
+/// they run out.
+fn increment_approval_id(approval_id: u64) -> u64 {
+    approval_id
+        .checked_add(1)
+        .unwrap_or_else(|| ContractError::ApprovalIdOverflow.panic())
+}
+
+impl Contract {
+    /// Records the approval of `account_id` on `token_id` of `owner_id`, without charging
+    /// for its storage, and returns its approval id.
+    pub(crate) fn internal_approve(
+        &mut self,
+        owner_id: &AccountId,
+        token_id: &TokenId,
+        account_id: &AccountId,
+    ) -> u64 {
+        crate::assert_valid_token_id(token_id);
+        let token_owner_id = self
+            .tokens
+            .owner_by_id
+            .get(token_id)
+            .unwrap_or_else(|| ContractError::TokenNotFound.panic());
+        require!(
+            &token_owner_id == owner_id,
+            ContractError::NotTokenOwner.message()
+        );
+        let approvals_by_id = self
+            .tokens
+            .approvals_by_id
+            .as_mut()
+            .unwrap_or_else(|| ContractError::ApprovalsNotSupported.panic());
+        let next_approval_id_by_id = self
+            .tokens
+            .next_approval_id_by_id
+            .as_mut()
+            .unwrap_or_else(|| ContractError::ApprovalsNotSupported.panic());
+
+        let approval_id = next_approval_id_by_id.get(token_id).unwrap_or(1);
+        let mut approvals = approvals_by_id.get(token_id).unwrap_or_default();
+        approvals.insert(account_id.clone(), approval_id);
+        approvals_by_id.insert(token_id, &approvals);
+        next_approval_id_by_id.insert(token_id, &increment_approval_id(approval_id));
+        // A new approval never inherits the expiry of a previous one
+        self.approval_expiries
+            .remove(&(token_id.clone(), account_id.clone()));
+        approval_id
+    }
+
+    /// Forgets the expiries of the approvals of `approved_account_ids` on `token_id`, once
+    /// those approvals are dropped.
+    pub(crate) fn remove_approval_expiries<'a>(
+        &mut self,
+        token_id: &TokenId,
+        approved_account_ids: impl IntoIterator<Item = &'a AccountId>,
+    ) {
+        for approved_account_id in approved_account_ids {
+            self.approval_expiries
+                .remove(&(token_id.clone(), approved_account_id.clone()));
+        }
+    }
+
+    pub(crate) fn current_approval_id(
+        &self,
+        token_id: &TokenId,
+        account_id: &AccountId,
+    ) -> Option<u64> {
+        self.tokens
+            .approvals_by_id
+            .as_ref()
+            .and_then(|by_id| by_id.get(token_id))
+            .and_then(|approvals| approvals.get(account_id).copied())
+    }
+
+    /// Whether the current approval of `account_id` on `token_id` has an expiry that passed.
+    /// Expiries recorded for an older approval id of the same account are ignored.
+    pub(crate) fn is_approval_expired(&self, token_id: &TokenId, account_id: &AccountId) -> bool {
+        let approval_id = match self.current_approval_id(token_id, account_id) {
+            Some(approval_id) => approval_id,
+            None => return false,
+        };
+        matches!(
+            self.approval_expiries.get(&(token_id.clone(), account_id.clone())),
+            Some((expiring_approval_id, expires_at_ns))
+                if expiring_approval_id == approval_id && env::block_timestamp() >= expires_at_ns
+        )
+    }
+
+    pub(crate) fn assert_approval_not_expired(&self, token_id: &TokenId, account_id: &AccountId) {
+        require!(
+            !self.is_approval_expired(token_id, account_id),
+            ContractError::ApprovalExpired.message()
+        );
+    }
+}
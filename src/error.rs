@@ -0,0 +1,121 @@
+use near_sdk::{env, FunctionError};
+use std::fmt;
+
+/// Failures shared by several methods of the contract. Each variant panics with the same
+/// message wherever it is raised, so callers can match on it, and implements `FunctionError`
+/// so that methods can return it with `#[handle_result]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContractError {
+    TokenNotFound,
+    TokenAlreadyExists,
+    TokenIdEmpty,
+    TokenIdTooLong,
+    TokenIdInvalidChars,
+    NotAuthorized,
+    NotAuthorizedMinter,
+    NotTokenOwner,
+    Paused,
+    MetadataFrozen,
+    MaxSupplyReached,
+    TokenSoulbound,
+    NotSoulboundOwner,
+    TokenLocked,
+    TransferInProgress,
+    SelfTransfer,
+    MemoRequired,
+    ReceiverNotAllowed,
+    ApprovalNotFound,
+    ApprovalExpired,
+    ApprovalIdMismatch,
+    ApprovalIdOverflow,
+    ApprovalsNotSupported,
+    DepositRequired,
+    ExpirationInPast,
+    SelfApproval,
+    MetadataNotSupported,
+    TransferNotInProgress,
+    NotPendingOwner,
+    MigrationModeNotSet,
+    ContractNotLocked,
+    OwnerIdMismatch,
+    OwnerMustTransfer,
+    SwapOfferNotFound,
+    CounterpartyNotOwner,
+    SwapNotOffered,
+    NotApprovedToLock,
+    TokenAlreadyLocked,
+    TokenNotLocked,
+    NotLockHolder,
+    RoyaltiesFrozen,
+    RoyaltyTooHigh,
+    TooManyPayoutReceivers,
+    MoreGasRequired,
+}
+
+impl ContractError {
+    /// The message the contract panics with.
+    pub fn message(&self) -> &'static str {
+        match self {
+            ContractError::TokenNotFound => "Token not found",
+            ContractError::TokenAlreadyExists => "Token already exists",
+            ContractError::TokenIdEmpty => "Token id must not be empty",
+            ContractError::TokenIdTooLong => "Token id must be at most 256 bytes",
+            ContractError::TokenIdInvalidChars => "Token id must not contain control characters",
+            ContractError::NotAuthorized => "Unauthorized",
+            ContractError::NotAuthorizedMinter => "Unauthorized minter",
+            ContractError::NotTokenOwner => "Predecessor must be the token owner",
+            ContractError::Paused => "Contract is paused",
+            ContractError::MetadataFrozen => "Metadata is frozen",
+            ContractError::MaxSupplyReached => "Max supply reached",
+            ContractError::TokenSoulbound => "Token is soulbound",
+            ContractError::NotSoulboundOwner => "Only the owner can burn a soulbound token",
+            ContractError::TokenLocked => "Token is locked",
+            ContractError::TransferInProgress => "Token transfer is in progress",
+            ContractError::SelfTransfer => "Cannot transfer to current owner",
+            ContractError::MemoRequired => "Memo is required",
+            ContractError::ReceiverNotAllowed => "Receiver is not allowed",
+            ContractError::ApprovalNotFound => "Approval not found",
+            ContractError::ApprovalExpired => "Approval has expired",
+            ContractError::ApprovalIdMismatch => {
+                "The actual approval_id is different from the given approval_id"
+            }
+            ContractError::ApprovalIdOverflow => "Approval ids of the token are exhausted",
+            ContractError::ApprovalsNotSupported => "NFT does not support Approval Management",
+            ContractError::DepositRequired => "Requires attached deposit of at least 1 yoctoNEAR",
+            ContractError::ExpirationInPast => "Expiration must be in the future",
+            ContractError::SelfApproval => "Can not approve yourself as operator",
+            ContractError::MetadataNotSupported => "NFT does not support Metadata",
+            ContractError::TransferNotInProgress => "Token transfer is not in progress",
+            ContractError::NotPendingOwner => "Only the pending owner can accept ownership",
+            ContractError::MigrationModeNotSet => "Migration mode is not set",
+            ContractError::ContractNotLocked => "Contract is not locked",
+            ContractError::OwnerIdMismatch => "owner_id must be the current owner of the token",
+            ContractError::OwnerMustTransfer => "The owner should call nft_transfer instead",
+            ContractError::SwapOfferNotFound => "Token has no swap offer",
+            ContractError::CounterpartyNotOwner => "Counterparty must own their token",
+            ContractError::SwapNotOffered => {
+                "Counterparty hasn't offered their token for this token"
+            }
+            ContractError::NotApprovedToLock => "Only an approved account can lock the token",
+            ContractError::TokenAlreadyLocked => "Token is already locked",
+            ContractError::TokenNotLocked => "Token is not locked",
+            ContractError::NotLockHolder => "Only the lock holder can unlock the token",
+            ContractError::RoyaltiesFrozen => "Royalties are frozen",
+            ContractError::RoyaltyTooHigh => "Royalties can not exceed 10000 basis points",
+            ContractError::TooManyPayoutReceivers => "Market cannot payout to that many receivers",
+            ContractError::MoreGasRequired => "More gas is required",
+        }
+    }
+}
+
+impl FunctionError for ContractError {
+    fn panic(&self) -> ! {
+        env::panic_str(self.message())
+    }
+}
+
+impl fmt::Display for ContractError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
@@ -1,13 +1,158 @@
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{Base64VecU8, U128};
+use near_sdk::serde_json::Value;
 use near_sdk::AccountId;
 use serde::{Deserialize, Serialize};
 use serde_with::skip_serializing_none;
+use std::fmt;
+use std::str::FromStr;
 
+/// A [NEP-297](https://nomicon.io/Standards/EventsFormat) event, logged as `EVENT_JSON:{...}`.
+///
+/// ```
+/// use near_sdk::AccountId;
+/// use rust_contract_events::{NearEvent, NftMintData};
+///
+/// let owner_id: AccountId = "alice.near".parse().unwrap();
+/// NearEvent::nft_mint(vec![NftMintData::new(&owner_id, vec!["token-1"], None)]).emit();
+/// ```
 #[derive(Serialize, Debug)]
 #[serde(tag = "standard")]
 #[serde(rename_all = "snake_case")]
 pub enum NearEvent<'a> {
     #[serde(borrow)]
     Nep171(Nep171Event<'a>),
+    #[serde(borrow)]
+    Nep178(Nep178Event<'a>),
+    #[serde(borrow)]
+    Storage(StorageEvent<'a>),
+}
+
+/// Destination of the logs of emitted events. [`NearEvent::emit`] and [`GenericEvent::emit`]
+/// log to the host through [`EnvLogSink`], while `emit_to` accepts any sink, e.g. a
+/// [`CapturingSink`] to check the events a piece of code emits.
+pub trait EventSink {
+    fn log(&mut self, log: &str);
+}
+
+/// Logs events to the host with `env::log_str`.
+pub struct EnvLogSink;
+
+impl EventSink for EnvLogSink {
+    fn log(&mut self, log: &str) {
+        near_sdk::env::log_str(log);
+    }
+}
+
+/// Keeps the logs of emitted events in memory instead of logging them.
+///
+/// ```
+/// use near_sdk::AccountId;
+/// use rust_contract_events::{CapturingSink, NearEvent, NftMintData, ParsedEventKind};
+///
+/// let owner_id: AccountId = "alice.near".parse().unwrap();
+/// let mut sink = CapturingSink::default();
+/// NearEvent::nft_mint(vec![NftMintData::new(&owner_id, vec!["token-1"], None)])
+///     .emit_to(&mut sink);
+/// let events = sink.events();
+/// assert_eq!(events.len(), 1);
+/// assert!(matches!(events[0].event_kind, ParsedEventKind::NftMint(_)));
+/// ```
+#[derive(Default, Debug)]
+pub struct CapturingSink {
+    pub logs: Vec<String>,
+}
+
+impl CapturingSink {
+    /// Parses the captured NEP-171, NEP-178 and storage events, skipping any other log, e.g.
+    /// generic events or events with base64 data.
+    pub fn events(&self) -> Vec<ParsedEvent> {
+        self.logs
+            .iter()
+            .filter_map(|log| ParsedEvent::parse_log(log).ok())
+            .collect()
+    }
+}
+
+impl EventSink for CapturingSink {
+    fn log(&mut self, log: &str) {
+        self.logs.push(log.to_string());
+    }
+}
+
+/// Logs the events the contract emits to the host, or in unit tests running inside
+/// [`capture_events`] to the [`CapturingSink`] it installed.
+pub(crate) struct ContractSink;
+
+impl EventSink for ContractSink {
+    fn log(&mut self, log: &str) {
+        #[cfg(test)]
+        {
+            let captured = CAPTURED_EVENTS.with(|sink| match sink.borrow_mut().as_mut() {
+                Some(sink) => {
+                    sink.log(log);
+                    true
+                }
+                None => false,
+            });
+            if captured {
+                return;
+            }
+        }
+        EnvLogSink.log(log);
+    }
+}
+
+#[cfg(test)]
+thread_local! {
+    static CAPTURED_EVENTS: std::cell::RefCell<Option<CapturingSink>> =
+        const { std::cell::RefCell::new(None) };
+}
+
+/// Runs `call`, keeping the events the contract emits meanwhile in the returned sink instead
+/// of logging them.
+#[cfg(test)]
+pub(crate) fn capture_events(call: impl FnOnce()) -> CapturingSink {
+    CAPTURED_EVENTS.with(|sink| *sink.borrow_mut() = Some(CapturingSink::default()));
+    call();
+    CAPTURED_EVENTS
+        .with(|sink| sink.borrow_mut().take())
+        .unwrap()
+}
+
+/// Versions of the NEP-171 standard events can be reported under.
+#[derive(
+    BorshDeserialize,
+    BorshSerialize,
+    Serialize,
+    Deserialize,
+    Debug,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Default,
+)]
+pub enum Nep171Version {
+    #[default]
+    #[serde(rename = "1.0.0")]
+    V1_0_0,
+    #[serde(rename = "1.1.0")]
+    V1_1_0,
+    #[serde(rename = "1.2.0")]
+    V1_2_0,
+}
+
+impl Nep171Version {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Nep171Version::V1_0_0 => "1.0.0",
+            Nep171Version::V1_1_0 => "1.1.0",
+            Nep171Version::V1_2_0 => "1.2.0",
+        }
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -29,6 +174,24 @@ pub enum Nep171EventKind<'a> {
     NftTransfer(Vec<NftTransferData<'a>>),
     #[serde(borrow)]
     NftBurn(Vec<NftBurnData<'a>>),
+    #[serde(borrow)]
+    NftMetadataUpdate(Vec<NftMetadataUpdateData<'a>>),
+    // NEP-171 names this event without the `nft_` prefix of the others
+    #[serde(borrow, rename = "contract_metadata_update")]
+    NftContractMetadataUpdate(Vec<NftContractMetadataUpdateData<'a>>),
+}
+
+impl Nep171EventKind<'_> {
+    /// The version of the standard that introduced this event.
+    pub fn min_version(&self) -> Nep171Version {
+        match self {
+            Nep171EventKind::NftMint(_)
+            | Nep171EventKind::NftTransfer(_)
+            | Nep171EventKind::NftBurn(_) => Nep171Version::V1_0_0,
+            Nep171EventKind::NftContractMetadataUpdate(_) => Nep171Version::V1_1_0,
+            Nep171EventKind::NftMetadataUpdate(_) => Nep171Version::V1_2_0,
+        }
+    }
 }
 
 #[skip_serializing_none]
@@ -56,6 +219,35 @@ impl<'a> NftMintData<'a> {
     }
 }
 
+/// Owned counterpart of [`NftMintData`], for events built from computed strings.
+#[skip_serializing_none]
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NftMintDataOwned {
+    pub owner_id: String,
+    pub token_ids: Vec<String>,
+    pub memo: Option<String>,
+}
+
+impl From<NftMintData<'_>> for NftMintDataOwned {
+    fn from(data: NftMintData<'_>) -> Self {
+        Self {
+            owner_id: data.owner_id.to_string(),
+            token_ids: data.token_ids.into_iter().map(str::to_string).collect(),
+            memo: data.memo.map(str::to_string),
+        }
+    }
+}
+
+impl<'a> From<&'a NftMintDataOwned> for NftMintData<'a> {
+    fn from(data: &'a NftMintDataOwned) -> Self {
+        Self {
+            owner_id: &data.owner_id,
+            token_ids: data.token_ids.iter().map(String::as_str).collect(),
+            memo: data.memo.as_deref(),
+        }
+    }
+}
+
 #[skip_serializing_none]
 #[derive(Serialize, Deserialize, Debug)]
 pub struct NftTransferData<'a> {
@@ -89,6 +281,73 @@ impl<'a> NftTransferData<'a> {
     }
 }
 
+/// Owned counterpart of [`NftTransferData`], for events built from computed strings. Reading
+/// it also accepts the `sender_id` and `receiver_id` names of older logs.
+#[skip_serializing_none]
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NftTransferDataOwned {
+    #[serde(alias = "sender_id")]
+    pub old_owner_id: String,
+    #[serde(alias = "receiver_id")]
+    pub new_owner_id: String,
+    pub token_ids: Vec<String>,
+    pub authorized_id: Option<String>,
+    pub memo: Option<String>,
+}
+
+impl From<NftTransferData<'_>> for NftTransferDataOwned {
+    fn from(data: NftTransferData<'_>) -> Self {
+        Self {
+            old_owner_id: data.old_owner_id.to_string(),
+            new_owner_id: data.new_owner_id.to_string(),
+            token_ids: data.token_ids.into_iter().map(str::to_string).collect(),
+            authorized_id: data.authorized_id.map(str::to_string),
+            memo: data.memo.map(str::to_string),
+        }
+    }
+}
+
+impl NftTransferDataOwned {
+    /// Reads the data of a transfer event from either its canonical JSON or the field names of
+    /// older logs.
+    ///
+    /// ```
+    /// use near_sdk::serde_json::json;
+    /// use rust_contract_events::NftTransferDataOwned;
+    ///
+    /// let canonical = json!({
+    ///     "old_owner_id": "alice.near",
+    ///     "new_owner_id": "bob.near",
+    ///     "token_ids": ["0"],
+    /// });
+    /// let legacy = json!({
+    ///     "sender_id": "alice.near",
+    ///     "receiver_id": "bob.near",
+    ///     "token_ids": ["0"],
+    /// });
+    /// assert_eq!(
+    ///     NftTransferDataOwned::from_lenient(canonical).unwrap(),
+    ///     NftTransferDataOwned::from_lenient(legacy).unwrap()
+    /// );
+    /// ```
+    pub fn from_lenient(value: Value) -> Result<Self, ParseError> {
+        near_sdk::serde_json::from_value(value)
+            .map_err(|error| ParseError::InvalidJson(error.to_string()))
+    }
+}
+
+impl<'a> From<&'a NftTransferDataOwned> for NftTransferData<'a> {
+    fn from(data: &'a NftTransferDataOwned) -> Self {
+        Self {
+            old_owner_id: &data.old_owner_id,
+            new_owner_id: &data.new_owner_id,
+            token_ids: data.token_ids.iter().map(String::as_str).collect(),
+            authorized_id: data.authorized_id.as_deref(),
+            memo: data.memo.as_deref(),
+        }
+    }
+}
+
 #[skip_serializing_none]
 #[derive(Serialize, Deserialize, Debug)]
 pub struct NftBurnData<'a> {
@@ -118,6 +377,346 @@ impl<'a> NftBurnData<'a> {
     }
 }
 
+/// Owned counterpart of [`NftBurnData`], for events built from computed strings.
+#[skip_serializing_none]
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NftBurnDataOwned {
+    pub owner_id: String,
+    pub token_ids: Vec<String>,
+    pub authorized_id: Option<String>,
+    pub memo: Option<String>,
+}
+
+impl From<NftBurnData<'_>> for NftBurnDataOwned {
+    fn from(data: NftBurnData<'_>) -> Self {
+        Self {
+            owner_id: data.owner_id.to_string(),
+            token_ids: data.token_ids.into_iter().map(str::to_string).collect(),
+            authorized_id: data.authorized_id.map(str::to_string),
+            memo: data.memo.map(str::to_string),
+        }
+    }
+}
+
+impl<'a> From<&'a NftBurnDataOwned> for NftBurnData<'a> {
+    fn from(data: &'a NftBurnDataOwned) -> Self {
+        Self {
+            owner_id: &data.owner_id,
+            token_ids: data.token_ids.iter().map(String::as_str).collect(),
+            authorized_id: data.authorized_id.as_deref(),
+            memo: data.memo.as_deref(),
+        }
+    }
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug)]
+pub struct NftMetadataUpdateData<'a> {
+    #[serde(borrow)]
+    pub token_ids: Vec<&'a str>,
+    #[serde(borrow)]
+    pub memo: Option<&'a str>,
+}
+
+impl<'a> NftMetadataUpdateData<'a> {
+    pub fn new(token_ids: Vec<&'a str>, memo: Option<&'a str>) -> NftMetadataUpdateData<'a> {
+        Self { token_ids, memo }
+    }
+}
+
+/// Owned counterpart of [`NftMetadataUpdateData`], for events built from computed strings.
+#[skip_serializing_none]
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NftMetadataUpdateDataOwned {
+    pub token_ids: Vec<String>,
+    pub memo: Option<String>,
+}
+
+impl From<NftMetadataUpdateData<'_>> for NftMetadataUpdateDataOwned {
+    fn from(data: NftMetadataUpdateData<'_>) -> Self {
+        Self {
+            token_ids: data.token_ids.into_iter().map(str::to_string).collect(),
+            memo: data.memo.map(str::to_string),
+        }
+    }
+}
+
+impl<'a> From<&'a NftMetadataUpdateDataOwned> for NftMetadataUpdateData<'a> {
+    fn from(data: &'a NftMetadataUpdateDataOwned) -> Self {
+        Self {
+            token_ids: data.token_ids.iter().map(String::as_str).collect(),
+            memo: data.memo.as_deref(),
+        }
+    }
+}
+
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug)]
+pub struct NftContractMetadataUpdateData<'a> {
+    #[serde(borrow)]
+    pub memo: Option<&'a str>,
+}
+
+impl<'a> NftContractMetadataUpdateData<'a> {
+    pub fn new(memo: Option<&'a str>) -> NftContractMetadataUpdateData<'a> {
+        Self { memo }
+    }
+}
+
+/// Owned counterpart of [`NftContractMetadataUpdateData`], for events built from computed strings.
+#[skip_serializing_none]
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NftContractMetadataUpdateDataOwned {
+    pub memo: Option<String>,
+}
+
+impl From<NftContractMetadataUpdateData<'_>> for NftContractMetadataUpdateDataOwned {
+    fn from(data: NftContractMetadataUpdateData<'_>) -> Self {
+        Self {
+            memo: data.memo.map(str::to_string),
+        }
+    }
+}
+
+impl<'a> From<&'a NftContractMetadataUpdateDataOwned> for NftContractMetadataUpdateData<'a> {
+    fn from(data: &'a NftContractMetadataUpdateDataOwned) -> Self {
+        Self {
+            memo: data.memo.as_deref(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Nep178Event<'a> {
+    pub version: &'static str,
+    #[serde(flatten)]
+    #[serde(borrow)]
+    pub event_kind: Nep178EventKind<'a>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "event", content = "data")]
+#[serde(rename_all = "snake_case")]
+#[allow(clippy::enum_variant_names)]
+pub enum Nep178EventKind<'a> {
+    #[serde(borrow)]
+    NftApprove(Vec<NftApproveData<'a>>),
+    #[serde(borrow)]
+    NftRevoke(Vec<NftRevokeData<'a>>),
+    #[serde(borrow)]
+    NftRevokeAll(Vec<NftRevokeAllData<'a>>),
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct NftApproveData<'a> {
+    #[serde(borrow)]
+    pub owner_id: &'a str,
+    #[serde(borrow)]
+    pub token_id: &'a str,
+    #[serde(borrow)]
+    pub approved_account_id: &'a str,
+    pub approval_id: u64,
+}
+
+impl<'a> NftApproveData<'a> {
+    pub fn new(
+        owner_id: &'a AccountId,
+        token_id: &'a str,
+        approved_account_id: &'a AccountId,
+        approval_id: u64,
+    ) -> NftApproveData<'a> {
+        Self {
+            owner_id: owner_id.as_str(),
+            token_id,
+            approved_account_id: approved_account_id.as_str(),
+            approval_id,
+        }
+    }
+}
+
+/// Owned counterpart of [`NftApproveData`], for events built from computed strings.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NftApproveDataOwned {
+    pub owner_id: String,
+    pub token_id: String,
+    pub approved_account_id: String,
+    pub approval_id: u64,
+}
+
+impl From<NftApproveData<'_>> for NftApproveDataOwned {
+    fn from(data: NftApproveData<'_>) -> Self {
+        Self {
+            owner_id: data.owner_id.to_string(),
+            token_id: data.token_id.to_string(),
+            approved_account_id: data.approved_account_id.to_string(),
+            approval_id: data.approval_id,
+        }
+    }
+}
+
+impl<'a> From<&'a NftApproveDataOwned> for NftApproveData<'a> {
+    fn from(data: &'a NftApproveDataOwned) -> Self {
+        Self {
+            owner_id: &data.owner_id,
+            token_id: &data.token_id,
+            approved_account_id: &data.approved_account_id,
+            approval_id: data.approval_id,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct NftRevokeData<'a> {
+    #[serde(borrow)]
+    pub owner_id: &'a str,
+    #[serde(borrow)]
+    pub token_id: &'a str,
+    #[serde(borrow)]
+    pub account_id: &'a str,
+}
+
+impl<'a> NftRevokeData<'a> {
+    pub fn new(
+        owner_id: &'a AccountId,
+        token_id: &'a str,
+        account_id: &'a AccountId,
+    ) -> NftRevokeData<'a> {
+        Self {
+            owner_id: owner_id.as_str(),
+            token_id,
+            account_id: account_id.as_str(),
+        }
+    }
+}
+
+/// Owned counterpart of [`NftRevokeData`], for events built from computed strings.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NftRevokeDataOwned {
+    pub owner_id: String,
+    pub token_id: String,
+    pub account_id: String,
+}
+
+impl From<NftRevokeData<'_>> for NftRevokeDataOwned {
+    fn from(data: NftRevokeData<'_>) -> Self {
+        Self {
+            owner_id: data.owner_id.to_string(),
+            token_id: data.token_id.to_string(),
+            account_id: data.account_id.to_string(),
+        }
+    }
+}
+
+impl<'a> From<&'a NftRevokeDataOwned> for NftRevokeData<'a> {
+    fn from(data: &'a NftRevokeDataOwned) -> Self {
+        Self {
+            owner_id: &data.owner_id,
+            token_id: &data.token_id,
+            account_id: &data.account_id,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct NftRevokeAllData<'a> {
+    #[serde(borrow)]
+    pub owner_id: &'a str,
+    #[serde(borrow)]
+    pub token_id: &'a str,
+}
+
+impl<'a> NftRevokeAllData<'a> {
+    pub fn new(owner_id: &'a AccountId, token_id: &'a str) -> NftRevokeAllData<'a> {
+        Self {
+            owner_id: owner_id.as_str(),
+            token_id,
+        }
+    }
+}
+
+/// Owned counterpart of [`NftRevokeAllData`], for events built from computed strings.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct NftRevokeAllDataOwned {
+    pub owner_id: String,
+    pub token_id: String,
+}
+
+impl From<NftRevokeAllData<'_>> for NftRevokeAllDataOwned {
+    fn from(data: NftRevokeAllData<'_>) -> Self {
+        Self {
+            owner_id: data.owner_id.to_string(),
+            token_id: data.token_id.to_string(),
+        }
+    }
+}
+
+impl<'a> From<&'a NftRevokeAllDataOwned> for NftRevokeAllData<'a> {
+    fn from(data: &'a NftRevokeAllDataOwned) -> Self {
+        Self {
+            owner_id: &data.owner_id,
+            token_id: &data.token_id,
+        }
+    }
+}
+
+/// Events of this contract's storage accounting, which no NEP covers.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct StorageEvent<'a> {
+    pub version: &'static str,
+    #[serde(flatten)]
+    #[serde(borrow)]
+    pub event_kind: StorageEventKind<'a>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+#[serde(tag = "event", content = "data")]
+#[serde(rename_all = "snake_case")]
+pub enum StorageEventKind<'a> {
+    #[serde(borrow)]
+    StorageRefund(Vec<StorageRefundData<'a>>),
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct StorageRefundData<'a> {
+    #[serde(borrow)]
+    pub account_id: &'a str,
+    pub amount: U128,
+}
+
+impl<'a> StorageRefundData<'a> {
+    pub fn new(account_id: &'a AccountId, amount: u128) -> StorageRefundData<'a> {
+        Self {
+            account_id: account_id.as_str(),
+            amount: U128(amount),
+        }
+    }
+}
+
+/// Owned counterpart of [`StorageRefundData`], for events built from computed strings.
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct StorageRefundDataOwned {
+    pub account_id: String,
+    pub amount: U128,
+}
+
+impl From<StorageRefundData<'_>> for StorageRefundDataOwned {
+    fn from(data: StorageRefundData<'_>) -> Self {
+        Self {
+            account_id: data.account_id.to_string(),
+            amount: data.amount,
+        }
+    }
+}
+
+impl<'a> From<&'a StorageRefundDataOwned> for StorageRefundData<'a> {
+    fn from(data: &'a StorageRefundDataOwned) -> Self {
+        Self {
+            account_id: &data.account_id,
+            amount: data.amount,
+        }
+    }
+}
+
 impl<'a> NearEvent<'a> {
     pub fn new_171(version: &'static str, event_kind: Nep171EventKind<'a>) -> Self {
         NearEvent::Nep171(Nep171Event {
@@ -130,6 +729,31 @@ impl<'a> NearEvent<'a> {
         NearEvent::new_171("1.0.0", event_kind)
     }
 
+    /// Creates a NEP-171 event reported under `version`, or under the version that introduced
+    /// the event if it is more recent.
+    pub fn new_171_versioned(version: Nep171Version, event_kind: Nep171EventKind<'a>) -> Self {
+        let version = version.max(event_kind.min_version());
+        NearEvent::new_171(version.as_str(), event_kind)
+    }
+
+    pub fn new_178(version: &'static str, event_kind: Nep178EventKind<'a>) -> Self {
+        NearEvent::Nep178(Nep178Event {
+            version,
+            event_kind,
+        })
+    }
+
+    pub fn new_178_v1(event_kind: Nep178EventKind<'a>) -> Self {
+        NearEvent::new_178("1.0.0", event_kind)
+    }
+
+    pub fn new_storage_v1(event_kind: StorageEventKind<'a>) -> Self {
+        NearEvent::Storage(StorageEvent {
+            version: "1.0.0",
+            event_kind,
+        })
+    }
+
     #[must_use = "don't forget to .emit() the event"]
     pub fn nft_burn(data: Vec<NftBurnData<'a>>) -> Self {
         NearEvent::new_171_v1(Nep171EventKind::NftBurn(data))
@@ -145,6 +769,52 @@ impl<'a> NearEvent<'a> {
         NearEvent::new_171_v1(Nep171EventKind::NftMint(data))
     }
 
+    #[must_use = "don't forget to .emit() the event"]
+    pub fn nft_metadata_update(data: Vec<NftMetadataUpdateData<'a>>) -> Self {
+        NearEvent::new_171_versioned(
+            Nep171Version::V1_0_0,
+            Nep171EventKind::NftMetadataUpdate(data),
+        )
+    }
+
+    #[must_use = "don't forget to .emit() the event"]
+    pub fn nft_contract_metadata_update(data: Vec<NftContractMetadataUpdateData<'a>>) -> Self {
+        NearEvent::new_171_versioned(
+            Nep171Version::V1_0_0,
+            Nep171EventKind::NftContractMetadataUpdate(data),
+        )
+    }
+
+    #[must_use = "don't forget to .emit() the event"]
+    pub fn nft_approve(data: Vec<NftApproveData<'a>>) -> Self {
+        NearEvent::new_178_v1(Nep178EventKind::NftApprove(data))
+    }
+
+    #[must_use = "don't forget to .emit() the event"]
+    pub fn nft_revoke(data: Vec<NftRevokeData<'a>>) -> Self {
+        NearEvent::new_178_v1(Nep178EventKind::NftRevoke(data))
+    }
+
+    #[must_use = "don't forget to .emit() the event"]
+    pub fn nft_revoke_all(data: Vec<NftRevokeAllData<'a>>) -> Self {
+        NearEvent::new_178_v1(Nep178EventKind::NftRevokeAll(data))
+    }
+
+    #[must_use = "don't forget to .emit() the event"]
+    pub fn storage_refund(data: Vec<StorageRefundData<'a>>) -> Self {
+        NearEvent::new_storage_v1(StorageEventKind::StorageRefund(data))
+    }
+
+    /// Reports a NEP-171 event under `version` instead of the default one, see
+    /// [`NearEvent::new_171_versioned`]. Events of other standards are returned unchanged.
+    #[must_use = "don't forget to .emit() the event"]
+    pub fn versioned(self, version: Nep171Version) -> Self {
+        match self {
+            NearEvent::Nep171(event) => NearEvent::new_171_versioned(version, event.event_kind),
+            event => event,
+        }
+    }
+
     pub(crate) fn to_json_string(&self) -> String {
         near_sdk::serde_json::to_string(self).unwrap()
     }
@@ -153,9 +823,328 @@ impl<'a> NearEvent<'a> {
         format!("EVENT_JSON:{}", self.to_json_string())
     }
 
+    /// Like [`Self::to_json_event_string`], but with the JSON of `data` encoded in base64,
+    /// see [`decode_base64_data`].
+    pub fn to_base64_data_event_string(&self) -> String {
+        with_base64_data(near_sdk::serde_json::to_value(self).unwrap())
+    }
+
+    /// Parses an `EVENT_JSON:` log back into an owned event, e.g. to check emitted logs.
+    ///
+    /// ```
+    /// use near_sdk::AccountId;
+    /// use rust_contract_events::{NearEvent, NftBurnData, ParsedEventKind};
+    ///
+    /// let owner_id: AccountId = "alice.near".parse().unwrap();
+    /// let log = NearEvent::nft_burn(vec![NftBurnData::new(&owner_id, vec!["0"], None, None)])
+    ///     .to_string();
+    /// let parsed = NearEvent::parse_log(&log).unwrap();
+    /// assert_eq!(parsed.standard, "nep171");
+    /// assert!(matches!(parsed.event_kind, ParsedEventKind::NftBurn(_)));
+    /// ```
+    pub fn parse_log(log: &str) -> Result<ParsedEvent, ParseError> {
+        ParsedEvent::parse_log(log)
+    }
+
+    /// Converts the event to its owned form, e.g. to store or encode it.
+    pub fn to_parsed(&self) -> ParsedEvent {
+        let json = near_sdk::serde_json::to_value(self).unwrap();
+        near_sdk::serde_json::from_value(json).unwrap()
+    }
+
+    /// Encodes the event with borsh, a more compact alternative to its JSON log.
+    pub fn to_borsh_bytes(&self) -> Vec<u8> {
+        self.to_parsed().try_to_vec().unwrap()
+    }
+
+    /// Decodes an event encoded by [`NearEvent::to_borsh_bytes`].
+    ///
+    /// ```
+    /// use near_sdk::AccountId;
+    /// use rust_contract_events::{NearEvent, NftMintData};
+    ///
+    /// let owner_id: AccountId = "alice.near".parse().unwrap();
+    /// let event = NearEvent::nft_mint(vec![NftMintData::new(&owner_id, vec!["token-1"], None)]);
+    /// let decoded = NearEvent::from_borsh_bytes(&event.to_borsh_bytes()).unwrap();
+    /// assert_eq!(decoded, event.to_parsed());
+    /// ```
+    pub fn from_borsh_bytes(bytes: &[u8]) -> Result<ParsedEvent, ParseError> {
+        ParsedEvent::try_from_slice(bytes)
+            .map_err(|error| ParseError::InvalidBorsh(error.to_string()))
+    }
+
+    /// Logs the event to the host. This is required to ensure that the event is triggered
+    /// and to consume the event.
+    pub fn emit(self) {
+        self.emit_to(&mut EnvLogSink);
+    }
+
+    /// Logs the event to `sink` instead of the host.
+    pub fn emit_to(self, sink: &mut impl EventSink) {
+        sink.log(&self.to_json_event_string());
+    }
+}
+
+/// Accumulates NEP-171 events during a call and logs them on [`EventBuffer::flush`] or
+/// [`EventBuffer::flush_with`]; events still buffered when it is dropped are never logged.
+/// Entries pushed right after an entry of the same event are merged into it, so a batch of
+/// mints is logged once. NEP-297 logs carry a single event, so different events, e.g. a burn
+/// followed by a mint, still take one log each.
+pub struct EventBuffer<'a> {
+    version: Nep171Version,
+    events: Vec<Nep171EventKind<'a>>,
+}
+
+impl<'a> EventBuffer<'a> {
+    /// Creates an empty buffer whose events are reported under `version`.
+    pub fn new(version: Nep171Version) -> Self {
+        Self {
+            version,
+            events: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, event_kind: Nep171EventKind<'a>) {
+        use Nep171EventKind::*;
+        match (self.events.last_mut(), event_kind) {
+            (Some(NftMint(data)), NftMint(more)) => data.extend(more),
+            (Some(NftTransfer(data)), NftTransfer(more)) => data.extend(more),
+            (Some(NftBurn(data)), NftBurn(more)) => data.extend(more),
+            (Some(NftMetadataUpdate(data)), NftMetadataUpdate(more)) => data.extend(more),
+            (Some(NftContractMetadataUpdate(data)), NftContractMetadataUpdate(more)) => {
+                data.extend(more)
+            }
+            (_, event_kind) => self.events.push(event_kind),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Logs the buffered events to the host in the order they were pushed and empties the
+    /// buffer. A contract with its own emission settings should use `flush_with` instead.
+    pub fn flush(&mut self) {
+        self.flush_with(NearEvent::emit);
+    }
+
+    /// Empties the buffer, passing its events in the order they were pushed to `emit`, e.g. to
+    /// log them with the emission settings of a contract.
+    pub fn flush_with(&mut self, mut emit: impl FnMut(NearEvent<'a>)) {
+        for event_kind in self.events.drain(..) {
+            emit(NearEvent::new_171_versioned(self.version, event_kind));
+        }
+    }
+}
+
+impl fmt::Display for NearEvent<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_json_event_string())
+    }
+}
+
+/// Owned form of an event read back from an `EVENT_JSON:` log, see [`ParsedEvent::parse_log`].
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ParsedEvent {
+    pub standard: String,
+    pub version: String,
+    #[serde(flatten)]
+    pub event_kind: ParsedEventKind,
+}
+
+#[derive(BorshSerialize, BorshDeserialize, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "event", content = "data")]
+#[serde(rename_all = "snake_case")]
+pub enum ParsedEventKind {
+    NftMint(Vec<NftMintDataOwned>),
+    NftTransfer(Vec<NftTransferDataOwned>),
+    NftBurn(Vec<NftBurnDataOwned>),
+    NftMetadataUpdate(Vec<NftMetadataUpdateDataOwned>),
+    #[serde(rename = "contract_metadata_update")]
+    NftContractMetadataUpdate(Vec<NftContractMetadataUpdateDataOwned>),
+    NftApprove(Vec<NftApproveDataOwned>),
+    NftRevoke(Vec<NftRevokeDataOwned>),
+    NftRevokeAll(Vec<NftRevokeAllDataOwned>),
+    StorageRefund(Vec<StorageRefundDataOwned>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    /// The log doesn't start with `EVENT_JSON:`.
+    MissingPrefix,
+    /// The log isn't the JSON of a known event.
+    InvalidJson(String),
+    /// The bytes aren't the borsh encoding of a known event.
+    InvalidBorsh(String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::MissingPrefix => f.write_str("log doesn't start with EVENT_JSON:"),
+            ParseError::InvalidJson(error) => write!(f, "invalid event JSON: {}", error),
+            ParseError::InvalidBorsh(error) => write!(f, "invalid event borsh: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+impl ParsedEvent {
+    /// Parses a log produced by [`NearEvent::emit`].
+    pub fn parse_log(log: &str) -> Result<ParsedEvent, ParseError> {
+        let json = log
+            .strip_prefix("EVENT_JSON:")
+            .ok_or(ParseError::MissingPrefix)?;
+        near_sdk::serde_json::from_str(json)
+            .map_err(|error| ParseError::InvalidJson(error.to_string()))
+    }
+}
+
+impl FromStr for ParsedEvent {
+    type Err = ParseError;
+
+    fn from_str(log: &str) -> Result<Self, Self::Err> {
+        ParsedEvent::parse_log(log)
+    }
+}
+
+/// A [NEP-297](https://nomicon.io/Standards/EventsFormat) event of any standard, for events
+/// that have no dedicated type in [`NearEvent`].
+///
+/// ```
+/// use near_sdk::serde_json::json;
+/// use rust_contract_events::GenericEvent;
+///
+/// GenericEvent::new("nep245", "1.0.0", "mt_mint", json!([{ "owner_id": "alice.near" }])).emit();
+/// ```
+#[skip_serializing_none]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GenericEvent {
+    pub standard: String,
+    pub version: String,
+    pub event: String,
+    pub data: Value,
+    #[serde(default)]
+    pub context: Option<BlockContext>,
+}
+
+/// Block an event was emitted in, for indexers to cross-check where it comes from.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct BlockContext {
+    pub block_height: u64,
+    pub epoch_height: u64,
+}
+
+impl BlockContext {
+    pub fn current() -> Self {
+        Self {
+            block_height: near_sdk::env::block_height(),
+            epoch_height: near_sdk::env::epoch_height(),
+        }
+    }
+}
+
+impl GenericEvent {
+    #[must_use = "don't forget to .emit() the event"]
+    pub fn new(
+        standard: impl Into<String>,
+        version: impl Into<String>,
+        event: impl Into<String>,
+        data: Value,
+    ) -> Self {
+        Self {
+            standard: standard.into(),
+            version: version.into(),
+            event: event.into(),
+            data,
+            context: None,
+        }
+    }
+
+    /// Records the current block in the `context` of the event.
+    #[must_use = "don't forget to .emit() the event"]
+    pub fn with_block_context(self) -> Self {
+        Self {
+            context: Some(BlockContext::current()),
+            ..self
+        }
+    }
+
+    pub(crate) fn to_json_string(&self) -> String {
+        near_sdk::serde_json::to_string(self).unwrap()
+    }
+
+    pub fn to_json_event_string(&self) -> String {
+        format!("EVENT_JSON:{}", self.to_json_string())
+    }
+
+    /// Like [`Self::to_json_event_string`], but with the JSON of `data` encoded in base64,
+    /// see [`decode_base64_data`].
+    pub fn to_base64_data_event_string(&self) -> String {
+        with_base64_data(near_sdk::serde_json::to_value(self).unwrap())
+    }
+
     /// Logs the event to the host. This is required to ensure that the event is triggered
     /// and to consume the event.
     pub fn emit(self) {
-        near_sdk::env::log_str(&self.to_json_event_string());
+        self.emit_to(&mut EnvLogSink);
+    }
+
+    /// Logs the event to `sink` instead of the host.
+    pub fn emit_to(self, sink: &mut impl EventSink) {
+        sink.log(&self.to_json_event_string());
     }
 }
+
+/// Replaces the `data` of an event with the base64 encoding of its JSON, keeping the standard,
+/// version and event name readable.
+fn with_base64_data(mut event: Value) -> String {
+    let data = near_sdk::serde_json::to_vec(&event["data"]).unwrap();
+    event["data"] = near_sdk::serde_json::to_value(Base64VecU8::from(data)).unwrap();
+    format!("EVENT_JSON:{}", event)
+}
+
+/// Reads an event logged with its `data` encoded in base64 back into its plain JSON.
+///
+/// ```
+/// use near_sdk::AccountId;
+/// use rust_contract_events::event::decode_base64_data;
+/// use rust_contract_events::{NearEvent, NftMintData};
+///
+/// let owner_id: AccountId = "alice.near".parse().unwrap();
+/// let event = NearEvent::nft_mint(vec![NftMintData::new(&owner_id, vec!["token-1"], None)]);
+/// let decoded = decode_base64_data(&event.to_base64_data_event_string()).unwrap();
+/// assert_eq!(decoded["event"], "nft_mint");
+/// assert_eq!(decoded["data"][0]["token_ids"][0], "token-1");
+/// ```
+pub fn decode_base64_data(log: &str) -> Result<Value, ParseError> {
+    let json = log
+        .strip_prefix("EVENT_JSON:")
+        .ok_or(ParseError::MissingPrefix)?;
+    let mut event: Value = near_sdk::serde_json::from_str(json)
+        .map_err(|error| ParseError::InvalidJson(error.to_string()))?;
+    let data: Base64VecU8 = near_sdk::serde_json::from_value(event["data"].take())
+        .map_err(|error| ParseError::InvalidJson(error.to_string()))?;
+    event["data"] = near_sdk::serde_json::from_slice(&data.0)
+        .map_err(|error| ParseError::InvalidJson(error.to_string()))?;
+    Ok(event)
+}
+
+/// Returns the JSON of the events logged in the mocked blockchain, in the order they were
+/// logged, for asserting on the events of a method in unit tests. Other logs are skipped.
+#[cfg(test)]
+pub(crate) fn logged_events() -> Vec<Value> {
+    near_sdk::test_utils::get_logs()
+        .iter()
+        .filter_map(|log| log.strip_prefix("EVENT_JSON:"))
+        .map(|json| near_sdk::serde_json::from_str(json).expect("Invalid event JSON"))
+        .collect()
+}
+
+/// Returns the JSON of the last event logged in the mocked blockchain, see [`logged_events`].
+/// Panics if no event was logged.
+#[cfg(test)]
+pub(crate) fn last_event() -> Value {
+    logged_events().pop().expect("No event was logged")
+}
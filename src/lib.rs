@@ -15,26 +15,118 @@ NOTES:
   - To prevent the deployed contract from being modified or deleted, it should not have any access
     keys on its account.
 */
-use near_contract_standards::non_fungible_token::core::NonFungibleTokenCore;
+use near_contract_standards::non_fungible_token::core::{
+    NonFungibleTokenCore, NonFungibleTokenResolver,
+};
 use near_contract_standards::non_fungible_token::metadata::{
     NFTContractMetadata, NonFungibleTokenMetadataProvider, TokenMetadata, NFT_METADATA_SPEC,
 };
 use near_contract_standards::non_fungible_token::NonFungibleToken;
 use near_contract_standards::non_fungible_token::{Token, TokenId};
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
-use near_sdk::collections::LazyOption;
+use near_sdk::collections::{LazyOption, LookupMap, UnorderedSet, Vector};
+use near_sdk::json_types::{Base64VecU8, U128, U64};
+use near_sdk::serde_json::{json, Value};
 use near_sdk::{
     assert_one_yocto, env, ext_contract, near_bindgen, require, AccountId, Balance,
-    BorshStorageKey, Gas, PanicOnDefault, Promise, PromiseOrValue,
+    BorshStorageKey, FunctionError, Gas, PanicOnDefault, Promise, PromiseOrValue, PromiseResult,
+    StorageUsage,
+};
+use serde::Serialize;
+mod approval;
+mod error;
+pub mod event;
+mod lock;
+mod migrate;
+mod royalty;
+#[cfg(feature = "testing")]
+mod testing;
+pub use error::ContractError;
+pub use event::{
+    BlockContext, CapturingSink, EnvLogSink, EventBuffer, EventSink, GenericEvent, NearEvent,
+    Nep171EventKind, Nep171Version, Nep178EventKind, NftApproveData, NftApproveDataOwned,
+    NftBurnData, NftBurnDataOwned, NftContractMetadataUpdateData,
+    NftContractMetadataUpdateDataOwned, NftMetadataUpdateData, NftMetadataUpdateDataOwned,
+    NftMintData, NftMintDataOwned, NftRevokeAllData, NftRevokeAllDataOwned, NftRevokeData,
+    NftRevokeDataOwned, NftTransferData, NftTransferDataOwned, ParseError, ParsedEvent,
+    ParsedEventKind, StorageEventKind, StorageRefundData, StorageRefundDataOwned,
 };
-mod event;
-use event::{NearEvent, NftBurnData, NftMintData, NftTransferData};
 
 #[near_bindgen]
 #[derive(BorshDeserialize, BorshSerialize, PanicOnDefault)]
 pub struct Contract {
     tokens: NonFungibleToken,
     metadata: LazyOption<NFTContractMetadata>,
+    royalties: LookupMap<TokenId, HashMap<AccountId, u32>>,
+    minters: UnorderedSet<AccountId>,
+    paused: bool,
+    /// Whether minting and burning are also frozen while the contract is paused.
+    pause_mints_and_burns: bool,
+    /// Account proposed as the next contract owner, until it accepts.
+    pending_owner: Option<AccountId>,
+    /// Tokens that can never be transferred, only burned by their owner.
+    soulbound: LookupMap<TokenId, bool>,
+    /// Approved accounts holding a lock on a token, e.g. during a marketplace listing.
+    locks: LookupMap<TokenId, AccountId>,
+    /// Approval id and expiration timestamp (in nanoseconds) of expiring approvals.
+    approval_expiries: LookupMap<(TokenId, AccountId), (u64, u64)>,
+    /// Number of tokens owned by each account, kept alongside the enumeration for O(1) reads.
+    balances: LookupMap<AccountId, u64>,
+    /// Version of the NEP-171 standard events are reported under.
+    event_version: Nep171Version,
+    /// Gas attached to `nft_on_transfer` in `nft_transfer_call`. When unset, the receiver gets
+    /// all of the prepaid gas left after reserving what the transfer and resolver need.
+    gas_for_on_transfer: Option<Gas>,
+    /// Number of tokens ever minted. Unlike the supply, it is never decreased by burns.
+    tokens_minted: u64,
+    /// Set by the deployer once every access key of the contract account has been removed.
+    contract_is_locked: bool,
+    /// Maximum number of tokens that can ever be minted. Burning doesn't free slots.
+    max_supply: Option<u64>,
+    /// `(owner_id, operator_id)` pairs where the operator may transfer any token of the owner.
+    operator_approvals: LookupMap<(AccountId, AccountId), bool>,
+    /// Once set, neither the contract nor the token metadata can be updated anymore.
+    metadata_frozen: bool,
+    /// Contracts `nft_transfer_call` may send tokens to. Any receiver is allowed when empty.
+    allowed_receivers: UnorderedSet<AccountId>,
+    /// Tokens sent by `nft_transfer_call` whose transfer hasn't been resolved yet. They can't
+    /// be transferred again until then, so a receiver can't move them while deciding.
+    transfers_in_flight: LookupMap<TokenId, bool>,
+    /// Royalties of tokens minted without perpetual royalties of their own.
+    default_royalty: HashMap<AccountId, u32>,
+    /// Whether the events of this contract outside of any NEP record the block they were
+    /// emitted in.
+    event_block_context: bool,
+    /// Token minted by each request of `nft_mint_idempotent`, for the latest requests only.
+    mint_requests: LookupMap<String, TokenId>,
+    /// Ring buffer of the request ids kept in `mint_requests`, oldest first from the cursor.
+    mint_request_ids: Vector<String>,
+    /// Number of requests ever recorded, whose remainder is the cursor in `mint_request_ids`.
+    mint_requests_recorded: u64,
+    /// Whether `nft_transfer` and `nft_transfer_call` reject transfers without a memo.
+    require_memo: bool,
+    /// Total supplies not reached yet that emit a `supply_milestone` event, in ascending order.
+    supply_milestones: Vec<u64>,
+    /// Number of tokens ever burned.
+    tokens_burned: u64,
+    /// Whether the contract logs events at all. Turned off while backfilling state, so that
+    /// indexers don't record historical activity twice.
+    emit_events: bool,
+    /// Whether token owners may update the royalties of their own tokens.
+    token_owner_updates_royalty: bool,
+    /// Whether token royalties can no longer be updated.
+    royalties_frozen: bool,
+    /// Block timestamp each existing token was minted at.
+    mint_times: LookupMap<TokenId, u64>,
+    /// Every mint in order, and so by timestamp, burned tokens included.
+    mint_log: Vector<(u64, TokenId)>,
+    /// Whether events are logged with the JSON of their `data` encoded in base64.
+    event_data_base64: bool,
+    /// Token each offered token may be swapped for with `nft_atomic_swap`, as offered by its
+    /// owner.
+    swap_offers: LookupMap<TokenId, TokenId>,
+    /// Whether the owner may emit the mint events of tokens minted before events were logged.
+    migration_mode: bool,
 }
 
 const DATA_IMAGE_SVG_NEAR_ICON: &str = "data:image/svg+xml,%3Csvg xmlns='http://www.w3.org/2000/svg' viewBox='0 0 288 288'%3E%3Cg id='l' data-name='l'%3E%3Cpath d='M187.58,79.81l-30.1,44.69a3.2,3.2,0,0,0,4.75,4.2L191.86,103a1.2,1.2,0,0,1,2,.91v80.46a1.2,1.2,0,0,1-2.12.77L102.18,77.93A15.35,15.35,0,0,0,90.47,72.5H87.34A15.34,15.34,0,0,0,72,87.84V201.16A15.34,15.34,0,0,0,87.34,216.5h0a15.35,15.35,0,0,0,13.08-7.31l30.1-44.69a3.2,3.2,0,0,0-4.75-4.2L96.14,186a1.2,1.2,0,0,1-2-.91V104.61a1.2,1.2,0,0,1,2.12-.77l89.55,107.23a15.35,15.35,0,0,0,11.71,5.43h3.13A15.34,15.34,0,0,0,216,201.16V87.84A15.34,15.34,0,0,0,200.66,72.5h0A15.35,15.35,0,0,0,187.58,79.81Z'/%3E%3C/g%3E%3C/svg%3E";
@@ -46,15 +138,88 @@ enum StorageKey {
     TokenMetadata,
     Enumeration,
     Approval,
+    TokensPerOwner { account_hash: Vec<u8> },
+    Royalties,
+    Minters,
+    Soulbound,
+    Locks,
+    ApprovalExpiries,
+    Balances,
+    OperatorApprovals,
+    AllowedReceivers,
+    TransfersInFlight,
+    MintRequests,
+    MintRequestIds,
+    MintTimes,
+    MintLog,
+    SwapOffers,
+}
+
+/// Fully-qualified `media` and `reference` URLs of a token, see `nft_token_resolved_urls`.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct ResolvedUrls {
+    pub media: Option<String>,
+    pub reference: Option<String>,
+}
+
+/// Status bits of a token, see `nft_token_flags`.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct TokenFlags {
+    pub soulbound: bool,
+    pub locked_by: Option<AccountId>,
+    /// Whether the metadata can no longer be updated, which applies to every token at once.
+    pub frozen: bool,
+}
+
+/// Number of tokens of an owner and a preview of their ids, see `nft_owner_summary`.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct OwnerSummary {
+    pub count: U128,
+    pub token_ids: Vec<TokenId>,
+}
+
+/// Storage held by the contract, see `nft_storage_report`.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct StorageReport {
+    pub total_tokens: U128,
+    pub total_bytes: U64,
+    pub total_cost: U128,
 }
 
 use std::collections::HashMap;
 
-const GAS_FOR_RESOLVE_TRANSFER: Gas = Gas(5_000_000_000_000);
+/// Gas of `nft_resolve_transfer`, which besides the standard rollback clears the in-flight
+/// marker, restores balances and emits events. Expiring approvals were dropped by the transfer
+/// and aren't restored. Running out of it would leave the token marked as in flight, see
+/// `clear_transfer_in_flight`.
+const GAS_FOR_RESOLVE_TRANSFER: Gas = Gas(15_000_000_000_000);
 const GAS_FOR_NFT_TRANSFER_CALL: Gas = Gas(25_000_000_000_000 + GAS_FOR_RESOLVE_TRANSFER.0);
+/// Number of the latest `nft_mint_idempotent` requests remembered to detect retries.
+const MAX_MINT_REQUESTS: u64 = 10_000;
+
+/// Least gas `nft_on_transfer` is called with, so that the receiver can at least run.
+const MIN_GAS_FOR_RECEIVER: Gas = Gas(5_000_000_000_000);
 
 const NO_DEPOSIT: Balance = 0;
 
+const OWNER_SUMMARY_DEFAULT_LIMIT: usize = 10;
+
+const OWNERSHIP_EVENT_STANDARD: &str = "contract_ownership";
+const OWNERSHIP_EVENT_VERSION: &str = "1.0.0";
+
+const SUPPLY_EVENT_STANDARD: &str = "nft_supply";
+const SUPPLY_EVENT_VERSION: &str = "1.0.0";
+
+/// Storage a mint takes besides the token metadata and royalty: the entries of the token in
+/// the owner, enumeration, balance and mint time collections, for token and account ids of up
+/// to 64 bytes. Measured with `env::storage_usage`, the first mint of a 64-byte token id to a
+/// new 64-byte owner, which also creates its token set and balance, takes 1_291 bytes.
+const MINT_STORAGE_OVERHEAD: StorageUsage = 1_300;
+
+/// Storage a royalty entry takes besides the royalty itself: the 40-byte record and a key made
+/// of the 1-byte collection prefix and a token id of up to 64 bytes with its 4-byte length.
+const ROYALTY_STORAGE_OVERHEAD: StorageUsage = 40 + 1 + 4 + 64;
+
 #[ext_contract(ext_self)]
 trait NFTResolver {
     fn nft_resolve_transfer(
@@ -80,7 +245,6 @@ pub trait NonFungibleTokenReceiver {
 
 #[near_bindgen]
 impl NonFungibleTokenCore for Contract {
-
     #[payable]
     fn nft_transfer(
         &mut self,
@@ -90,8 +254,10 @@ impl NonFungibleTokenCore for Contract {
         memo: Option<String>,
     ) {
         assert_one_yocto();
+        self.assert_not_paused();
+        self.assert_memo_if_required(&memo);
         let sender_id = env::predecessor_account_id();
-        self.tokens.internal_transfer(
+        let (old_owner_id, _) = self.internal_transfer(
             &sender_id,
             &receiver_id,
             &token_id,
@@ -99,20 +265,14 @@ impl NonFungibleTokenCore for Contract {
             memo.clone(),
         );
 
-        // Create a NearEvent
-        let old_owner_id = self
-            .tokens
-            .owner_by_id
-            .get(&token_id)
-            .unwrap_or_else(|| env::panic_str("Token not found"));
-        NearEvent::nft_transfer(vec![NftTransferData::new(
+        // Create a NearEvent, naming the sender when it acted as an approved account
+        self.emit_event(NearEvent::nft_transfer(vec![NftTransferData::new(
             &old_owner_id,
             &receiver_id,
             vec![&token_id],
-            None,
+            Some(&sender_id).filter(|sender_id| *sender_id != &old_owner_id),
             memo.as_deref(),
-        )])
-        .emit();
+        )]));
     }
 
     fn nft_transfer_call(
@@ -124,14 +284,50 @@ impl NonFungibleTokenCore for Contract {
         msg: String,
     ) -> PromiseOrValue<bool> {
         assert_one_yocto();
+        self.assert_not_paused();
+        self.assert_memo_if_required(&memo);
         require!(
-            env::prepaid_gas() > GAS_FOR_NFT_TRANSFER_CALL,
-            "More gas is required"
+            self.allowed_receivers.is_empty() || self.allowed_receivers.contains(&receiver_id),
+            ContractError::ReceiverNotAllowed.message()
         );
+        let gas_for_on_transfer = match self.gas_for_on_transfer {
+            Some(gas_for_on_transfer) => {
+                require!(
+                    env::prepaid_gas() >= GAS_FOR_NFT_TRANSFER_CALL + gas_for_on_transfer,
+                    ContractError::MoreGasRequired.message()
+                );
+                gas_for_on_transfer
+            }
+            None => {
+                require!(
+                    env::prepaid_gas() >= GAS_FOR_NFT_TRANSFER_CALL + MIN_GAS_FOR_RECEIVER,
+                    ContractError::MoreGasRequired.message()
+                );
+                env::prepaid_gas() - GAS_FOR_NFT_TRANSFER_CALL
+            }
+        };
         let sender_id = env::predecessor_account_id();
-        let (old_owner, old_approvals) =
-            self.tokens
-                .internal_transfer(&sender_id, &receiver_id, &token_id, approval_id, memo);
+        let (old_owner, old_approvals) = self.internal_transfer(
+            &sender_id,
+            &receiver_id,
+            &token_id,
+            approval_id,
+            memo.clone(),
+        );
+        self.transfers_in_flight.insert(&token_id, &true);
+
+        // Create a NearEvent, naming the sender when it acted as an approved account. It is
+        // logged before the receiver is called, so indexers see the transfer before the transfer
+        // back logged by `nft_resolve_transfer`, if any.
+        let authorized_id = Some(&sender_id).filter(|sender_id| *sender_id != &old_owner);
+        self.emit_event(NearEvent::nft_transfer(vec![NftTransferData::new(
+            &old_owner,
+            &receiver_id,
+            vec![&token_id],
+            authorized_id,
+            memo.as_deref(),
+        )]));
+
         // Initiating receiver's call and the callback
         ext_receiver::nft_on_transfer(
             sender_id,
@@ -140,7 +336,7 @@ impl NonFungibleTokenCore for Contract {
             msg,
             receiver_id.clone(),
             NO_DEPOSIT,
-            env::prepaid_gas() - GAS_FOR_NFT_TRANSFER_CALL,
+            gas_for_on_transfer,
         )
         .then(ext_self::nft_resolve_transfer(
             old_owner,
@@ -175,6 +371,77 @@ impl NonFungibleTokenCore for Contract {
     }
 }
 
+#[near_bindgen]
+impl NonFungibleTokenResolver for Contract {
+    /// Returns true if the token was not returned to `previous_owner_id`. On rollback the token
+    /// and the approvals it had before `nft_transfer_call` are restored to `previous_owner_id`,
+    /// unless the receiver already burned or transferred it away, in which case true is
+    /// returned and nothing is emitted.
+    #[private]
+    fn nft_resolve_transfer(
+        &mut self,
+        previous_owner_id: AccountId,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        approved_account_ids: Option<HashMap<AccountId, u64>>,
+    ) -> bool {
+        self.transfers_in_flight.remove(&token_id);
+
+        // Get whether token should be returned
+        let must_revert = match env::promise_result(0) {
+            PromiseResult::NotReady => env::abort(),
+            PromiseResult::Successful(value) => {
+                near_sdk::serde_json::from_slice::<bool>(&value).unwrap_or(true)
+            }
+            PromiseResult::Failed => true,
+        };
+        if !must_revert {
+            return true;
+        }
+
+        // The token can't be returned if the receiver already transferred it away or burned it.
+        match self.tokens.owner_by_id.get(&token_id) {
+            Some(current_owner) if current_owner == receiver_id => {}
+            Some(_) => return true,
+            None => {
+                // Refund the storage of the approvals cleared by the transfer
+                if let Some(approved_account_ids) = approved_account_ids {
+                    refund_approved_account_ids(&previous_owner_id, &approved_account_ids);
+                }
+                return true;
+            }
+        }
+
+        self.tokens
+            .internal_transfer_unguarded(&token_id, &receiver_id, &previous_owner_id);
+        self.decrement_balance(&receiver_id);
+        self.increment_balance(&previous_owner_id);
+        #[cfg(test)]
+        self.assert_counter_invariants();
+
+        // Drop the approvals set by the receiver and restore those of the previous owner
+        if let Some(by_id) = &mut self.tokens.approvals_by_id {
+            if let Some(receiver_approvals) = by_id.get(&token_id) {
+                refund_approved_account_ids(&receiver_id, &receiver_approvals);
+            }
+            match approved_account_ids {
+                Some(approved_account_ids) => by_id.insert(&token_id, &approved_account_ids),
+                None => by_id.remove(&token_id),
+            };
+        }
+
+        // Create a NearEvent for the token going back to its previous owner
+        self.emit_event(NearEvent::nft_transfer(vec![NftTransferData::new(
+            &receiver_id,
+            &previous_owner_id,
+            vec![&token_id],
+            None,
+            None,
+        )]));
+        false
+    }
+}
+
 #[near_bindgen]
 impl Contract {
     /// Initializes the contract owned by `owner_id` with
@@ -192,68 +459,5341 @@ impl Contract {
                 reference: None,
                 reference_hash: None,
             },
+            None,
+            None,
+            None,
+            None,
+            None,
         )
     }
 
+    /// `event_version` selects the version of the NEP-171 standard events are reported under,
+    /// defaulting to 1.0.0. Events introduced in a later version always report that version.
+    /// `max_supply` caps the number of tokens that can ever be minted, burned ones included.
+    /// `default_royalty` applies to tokens minted without perpetual royalties.
+    /// `require_memo` makes every transfer method taking a memo reject transfers without one. A `supply_milestone` event is emitted the first time `nft_total_supply` reaches
+    /// each of `supply_milestones`.
     #[init]
-    pub fn new(owner_id: AccountId, metadata: NFTContractMetadata) -> Self {
+    pub fn new(
+        owner_id: AccountId,
+        metadata: NFTContractMetadata,
+        event_version: Option<Nep171Version>,
+        max_supply: Option<u64>,
+        default_royalty: Option<HashMap<AccountId, u32>>,
+        require_memo: Option<bool>,
+        supply_milestones: Option<Vec<u64>>,
+    ) -> Self {
         assert!(!env::state_exists(), "Already initialized");
-        metadata.assert_valid();
+        validate_contract_metadata(&metadata);
+        let default_royalty = default_royalty.unwrap_or_default();
+        royalty::assert_valid_royalty(&default_royalty);
+        let mut supply_milestones = supply_milestones.unwrap_or_default();
+        supply_milestones.sort_unstable();
+        supply_milestones.dedup();
         Self {
-            tokens: NonFungibleToken::new(
-                StorageKey::NonFungibleToken,
-                owner_id,
-                Some(StorageKey::TokenMetadata),
-                Some(StorageKey::Enumeration),
-                Some(StorageKey::Approval),
-            ),
-            metadata: LazyOption::new(StorageKey::Metadata, Some(&metadata)),
+            event_version: event_version.unwrap_or_default(),
+            max_supply,
+            default_royalty,
+            require_memo: require_memo.unwrap_or(false),
+            supply_milestones,
+            ..Self::from_parts(
+                NonFungibleToken::new(
+                    StorageKey::NonFungibleToken,
+                    owner_id,
+                    Some(StorageKey::TokenMetadata),
+                    Some(StorageKey::Enumeration),
+                    Some(StorageKey::Approval),
+                ),
+                LazyOption::new(StorageKey::Metadata, Some(&metadata)),
+            )
         }
     }
 
+    /// Mints `token_id` to `receiver_id`. The storage the token takes is paid from the attached
+    /// deposit, and the whole excess is refunded to the caller along with a `storage_refund`
+    /// event, unless it is a single yoctoNEAR, which is kept.
     #[payable]
     pub fn nft_mint(
         &mut self,
         token_id: TokenId,
         receiver_id: AccountId,
         token_metadata: TokenMetadata,
+        perpetual_royalties: Option<HashMap<AccountId, u32>>,
+        soulbound: Option<bool>,
+        memo: Option<String>,
     ) -> Token {
         // Some implementations are skipped in this example
-        let token =
-            self.tokens
-                .internal_mint(token_id.clone(), receiver_id.clone(), Some(token_metadata));
+        self.assert_minter();
+        self.assert_mint_and_burn_not_paused();
+        let initial_storage_usage = env::storage_usage();
+        let token = self.internal_mint_without_refund(
+            token_id.clone(),
+            receiver_id.clone(),
+            token_metadata,
+        );
+        if let Some(perpetual_royalties) = perpetual_royalties {
+            royalty::assert_valid_royalty(&perpetual_royalties);
+            self.royalties.insert(&token_id, &perpetual_royalties);
+        }
+        if soulbound.unwrap_or(false) {
+            self.soulbound.insert(&token_id, &true);
+        }
+
+        // Return any extra attached deposit not used for storage
+        self.refund_deposit(env::storage_usage() - initial_storage_usage);
 
         // Create a NearEvent
-        NearEvent::nft_mint(vec![NftMintData::new(&receiver_id, vec![&token_id], None)]).emit();
+        self.emit_event(NearEvent::nft_mint(vec![NftMintData::new(
+            &receiver_id,
+            vec![&token_id],
+            memo.as_deref(),
+        )]));
+        self.emit_reached_supply_milestones();
         token
     }
 
+    /// Mints like `nft_mint`, unless a mint with the same `request_id` was already made, in
+    /// which case the token it minted is returned and the attached deposit refunded. Only the
+    /// latest 10 000 request ids are remembered.
     #[payable]
-    pub fn nft_burn(&mut self, token_id: Option<TokenId>) {
-        // Some implementations are skipped in this example
+    pub fn nft_mint_idempotent(
+        &mut self,
+        request_id: String,
+        token_id: TokenId,
+        receiver_id: AccountId,
+        token_metadata: TokenMetadata,
+    ) -> Token {
+        self.assert_minter();
+        self.assert_mint_and_burn_not_paused();
+        if let Some(minted_token_id) = self.mint_requests.get(&request_id) {
+            self.refund_deposit(0);
+            return self
+                .nft_token(minted_token_id)
+                .unwrap_or_else(|| ContractError::TokenNotFound.panic());
+        }
+        let initial_storage_usage = env::storage_usage();
+        let token = self.internal_mint_without_refund(
+            token_id.clone(),
+            receiver_id.clone(),
+            token_metadata,
+        );
+        self.record_mint_request(request_id, &token_id);
+
+        // Return any extra attached deposit not used for storage
+        self.refund_deposit(env::storage_usage() - initial_storage_usage);
 
         // Create a NearEvent
-        let owner_id = self
-            .tokens
-            .owner_by_id
-            .get(&token_id.as_ref().unwrap())
-            .unwrap_or_else(|| env::panic_str("Token not found"));
-        NearEvent::nft_burn(vec![NftBurnData::new(
-            &owner_id,
-            vec![&token_id.unwrap()],
+        self.emit_event(NearEvent::nft_mint(vec![NftMintData::new(
+            &receiver_id,
+            vec![&token_id],
             None,
+        )]));
+        self.emit_reached_supply_milestones();
+        token
+    }
+
+    /// Mints like `nft_mint` for a primary sale. NEP-171 has no price field, so a `price` is
+    /// recorded in the memo of the mint event as `{"price":"<yoctoNEAR>"}`.
+    #[payable]
+    pub fn nft_mint_sale(
+        &mut self,
+        token_id: TokenId,
+        receiver_id: AccountId,
+        token_metadata: TokenMetadata,
+        price: Option<U128>,
+    ) -> Token {
+        let memo = price.map(|price| json!({ "price": price }).to_string());
+        self.nft_mint(token_id, receiver_id, token_metadata, None, None, memo)
+    }
+
+    /// Mints `token_id` to `receiver_id` and approves `operator` on it, e.g. to list a drop on
+    /// a marketplace right away. Emits a mint event and an approve event, and the attached
+    /// deposit must cover the storage of both. Returns the token and the approval id.
+    #[payable]
+    pub fn nft_mint_and_approve(
+        &mut self,
+        token_id: TokenId,
+        receiver_id: AccountId,
+        token_metadata: TokenMetadata,
+        operator: AccountId,
+    ) -> (Token, u64) {
+        self.assert_minter();
+        self.assert_mint_and_burn_not_paused();
+        let initial_storage_usage = env::storage_usage();
+        self.internal_mint_without_refund(token_id.clone(), receiver_id.clone(), token_metadata);
+        let approval_id = self.internal_approve(&receiver_id, &token_id, &operator);
+
+        // Return any extra attached deposit not used for storage
+        self.refund_deposit(env::storage_usage() - initial_storage_usage);
+
+        // Create the NearEvents
+        self.emit_event(NearEvent::nft_mint(vec![NftMintData::new(
+            &receiver_id,
+            vec![&token_id],
             None,
-        )])
-        .emit();
+        )]));
+        self.emit_reached_supply_milestones();
+        self.emit_event(NearEvent::nft_approve(vec![NftApproveData::new(
+            &receiver_id,
+            &token_id,
+            &operator,
+            approval_id,
+        )]));
+        let token = self.nft_token(token_id).unwrap();
+        (token, approval_id)
     }
-}
 
-near_contract_standards::impl_non_fungible_token_approval!(Contract, tokens);
-near_contract_standards::impl_non_fungible_token_enumeration!(Contract, tokens);
+    /// Mints a token to `receiver_id` with the next sequential id (see `next_token_id`), so ids
+    /// are never reused, even after burns. Minting explicit numeric ids through `nft_mint`
+    /// in the same collection may make a later call fail with "Token already exists".
+    #[payable]
+    pub fn nft_mint_auto(
+        &mut self,
+        receiver_id: AccountId,
+        token_metadata: TokenMetadata,
+    ) -> Token {
+        self.assert_minter();
+        self.assert_mint_and_burn_not_paused();
+        let initial_storage_usage = env::storage_usage();
+        let token_id = self.next_token_id();
+        let token = self.internal_mint_without_refund(
+            token_id.clone(),
+            receiver_id.clone(),
+            token_metadata,
+        );
 
-#[near_bindgen]
-impl NonFungibleTokenMetadataProvider for Contract {
-    fn nft_metadata(&self) -> NFTContractMetadata {
-        self.metadata.get().unwrap()
+        // Return any extra attached deposit not used for storage
+        self.refund_deposit(env::storage_usage() - initial_storage_usage);
+
+        // Create a NearEvent
+        self.emit_event(NearEvent::nft_mint(vec![NftMintData::new(
+            &receiver_id,
+            vec![&token_id],
+            None,
+        )]));
+        self.emit_reached_supply_milestones();
+        token
+    }
+
+    /// Mints every token of `token_ids` to `receiver_id` and emits a single mint event
+    /// grouping them. The attached deposit must cover the storage of the whole batch.
+    #[payable]
+    pub fn nft_batch_mint(
+        &mut self,
+        token_ids: Vec<TokenId>,
+        receiver_id: AccountId,
+        token_metadatas: Vec<TokenMetadata>,
+    ) -> Vec<Token> {
+        require!(!token_ids.is_empty(), "token_ids must not be empty");
+        require!(
+            token_ids.len() == token_metadatas.len(),
+            "token_ids and token_metadatas must have the same length"
+        );
+        self.assert_minter();
+        self.assert_mint_and_burn_not_paused();
+        let initial_storage_usage = env::storage_usage();
+
+        let tokens = token_ids
+            .iter()
+            .zip(token_metadatas)
+            .map(|(token_id, token_metadata)| {
+                self.internal_mint_without_refund(
+                    token_id.clone(),
+                    receiver_id.clone(),
+                    token_metadata,
+                )
+            })
+            .collect();
+
+        // Return any extra attached deposit not used for storage
+        self.refund_deposit(env::storage_usage() - initial_storage_usage);
+
+        // Create a NearEvent
+        self.emit_event(NearEvent::nft_mint(vec![NftMintData::new(
+            &receiver_id,
+            token_ids.iter().map(|token_id| token_id.as_str()).collect(),
+            None,
+        )]));
+        self.emit_reached_supply_milestones();
+        tokens
+    }
+
+    /// Mints `copies` editions of `token_metadata` to `receiver_id`, with ids
+    /// `{base_token_id}:1` to `{base_token_id}:{copies}` and `copies` set in each metadata.
+    /// Emits a single mint event with every edition id.
+    #[payable]
+    pub fn nft_mint_editions(
+        &mut self,
+        base_token_id: TokenId,
+        receiver_id: AccountId,
+        token_metadata: TokenMetadata,
+        copies: u64,
+    ) -> Vec<Token> {
+        require!(copies > 0, "copies must be positive");
+        self.assert_minter();
+        self.assert_mint_and_burn_not_paused();
+        let initial_storage_usage = env::storage_usage();
+
+        let token_metadata = TokenMetadata {
+            copies: Some(copies),
+            ..token_metadata
+        };
+        let token_ids: Vec<TokenId> = (1..=copies)
+            .map(|edition| format!("{}:{}", base_token_id, edition))
+            .collect();
+        let tokens = token_ids
+            .iter()
+            .map(|token_id| {
+                self.internal_mint_without_refund(
+                    token_id.clone(),
+                    receiver_id.clone(),
+                    token_metadata.clone(),
+                )
+            })
+            .collect();
+
+        // Return any extra attached deposit not used for storage
+        self.refund_deposit(env::storage_usage() - initial_storage_usage);
+
+        // Create a NearEvent
+        self.emit_event(NearEvent::nft_mint(vec![NftMintData::new(
+            &receiver_id,
+            token_ids.iter().map(|token_id| token_id.as_str()).collect(),
+            None,
+        )]));
+        self.emit_reached_supply_milestones();
+        tokens
+    }
+
+    /// Mints each `(token_id, receiver_id, token_metadata)` of `mints` and emits a single mint
+    /// event with one entry per receiver. The attached deposit must cover the storage of the
+    /// whole batch.
+    #[payable]
+    pub fn nft_batch_mint_to(
+        &mut self,
+        mints: Vec<(TokenId, AccountId, TokenMetadata)>,
+    ) -> Vec<Token> {
+        require!(!mints.is_empty(), "mints must not be empty");
+        self.assert_minter();
+        self.assert_mint_and_burn_not_paused();
+        let initial_storage_usage = env::storage_usage();
+
+        let mut tokens = Vec::with_capacity(mints.len());
+        let mut groups: Vec<(AccountId, Vec<TokenId>)> = Vec::new();
+        for (token_id, receiver_id, token_metadata) in mints {
+            tokens.push(self.internal_mint_without_refund(
+                token_id.clone(),
+                receiver_id.clone(),
+                token_metadata,
+            ));
+            match groups.iter_mut().find(|(owner, _)| owner == &receiver_id) {
+                Some((_, token_ids)) => token_ids.push(token_id),
+                None => groups.push((receiver_id, vec![token_id])),
+            }
+        }
+
+        // Return any extra attached deposit not used for storage
+        self.refund_deposit(env::storage_usage() - initial_storage_usage);
+
+        // Create a NearEvent
+        self.emit_event(NearEvent::nft_mint(
+            groups
+                .iter()
+                .map(|(owner_id, token_ids)| {
+                    NftMintData::new(
+                        owner_id,
+                        token_ids.iter().map(|token_id| token_id.as_str()).collect(),
+                        None,
+                    )
+                })
+                .collect(),
+        ));
+        self.emit_reached_supply_milestones();
+        tokens
+    }
+
+    /// Allows `account_id` to mint tokens. Only the contract owner may call it.
+    #[payable]
+    pub fn add_minter(&mut self, account_id: AccountId) {
+        assert_one_yocto();
+        self.assert_owner();
+        self.minters.insert(&account_id);
+    }
+
+    /// Revokes the right of `account_id` to mint tokens. Only the contract owner may call it.
+    #[payable]
+    pub fn remove_minter(&mut self, account_id: AccountId) {
+        assert_one_yocto();
+        self.assert_owner();
+        self.minters.remove(&account_id);
+    }
+
+    /// Returns a page of the accounts allowed to mint, besides the contract owner, in the order
+    /// they were added as long as none was removed.
+    pub fn minters(&self, from_index: Option<U128>, limit: Option<u64>) -> Vec<AccountId> {
+        let start_index: u128 = from_index.map(From::from).unwrap_or_default();
+        require!(
+            u128::from(self.minters.len()) >= start_index,
+            "Out of bounds, please use a smaller from_index."
+        );
+        let limit = limit.map_or(usize::MAX, |limit| limit as usize);
+        require!(limit != 0, "Cannot provide limit of 0.");
+        self.minters
+            .iter()
+            .skip(start_index as usize)
+            .take(limit)
+            .collect()
+    }
+
+    /// Allows `nft_transfer_call` to send tokens to `account_id`. Once any receiver is listed,
+    /// only listed receivers are allowed. Only the contract owner may call it.
+    #[payable]
+    pub fn add_allowed_receiver(&mut self, account_id: AccountId) {
+        assert_one_yocto();
+        self.assert_owner();
+        self.allowed_receivers.insert(&account_id);
+    }
+
+    /// Removes `account_id` from the receivers of `nft_transfer_call`. Only the contract owner
+    /// may call it.
+    #[payable]
+    pub fn remove_allowed_receiver(&mut self, account_id: AccountId) {
+        assert_one_yocto();
+        self.assert_owner();
+        self.allowed_receivers.remove(&account_id);
+    }
+
+    /// Clears the in-flight marker of `token_id`, left behind if its `nft_resolve_transfer`
+    /// failed, so that the token can be transferred again. Only the contract owner may call it,
+    /// after checking that no receipt of the `nft_transfer_call` of the token is still pending.
+    #[payable]
+    pub fn clear_transfer_in_flight(&mut self, token_id: TokenId) {
+        assert_one_yocto();
+        self.assert_owner();
+        require!(
+            self.transfers_in_flight.remove(&token_id).is_some(),
+            ContractError::TransferNotInProgress.message()
+        );
+    }
+
+    /// Proposes `new_owner` as the next contract owner. The ownership only changes once
+    /// `new_owner` calls `accept_ownership`. Only the contract owner may call it.
+    #[payable]
+    pub fn propose_owner(&mut self, new_owner: AccountId) {
+        assert_one_yocto();
+        self.assert_owner();
+        self.pending_owner = Some(new_owner);
+    }
+
+    /// Makes the pending owner the contract owner. Only the pending owner may call it.
+    #[payable]
+    pub fn accept_ownership(&mut self) {
+        assert_one_yocto();
+        let predecessor_id = env::predecessor_account_id();
+        require!(
+            self.pending_owner.as_ref() == Some(&predecessor_id),
+            ContractError::NotPendingOwner.message()
+        );
+        self.pending_owner = None;
+        let previous_owner = std::mem::replace(&mut self.tokens.owner_id, predecessor_id);
+
+        // Create a GenericEvent, as no NEP covers contract ownership
+        self.emit_generic_event(GenericEvent::new(
+            OWNERSHIP_EVENT_STANDARD,
+            OWNERSHIP_EVENT_VERSION,
+            "ownership_transferred",
+            json!([{ "previous_owner": previous_owner, "new_owner": self.tokens.owner_id }]),
+        ));
+    }
+
+    /// Freezes transfers until `unpause` is called. Only the contract owner may call it.
+    #[payable]
+    pub fn pause(&mut self) {
+        assert_one_yocto();
+        self.assert_owner();
+        self.paused = true;
+    }
+
+    /// Resumes transfers. Only the contract owner may call it.
+    #[payable]
+    pub fn unpause(&mut self) {
+        assert_one_yocto();
+        self.assert_owner();
+        self.paused = false;
+    }
+
+    /// Sets whether minting and burning are frozen as well while the contract is paused.
+    /// Only the contract owner may call it.
+    #[payable]
+    pub fn set_pause_mints_and_burns(&mut self, pause_mints_and_burns: bool) {
+        assert_one_yocto();
+        self.assert_owner();
+        self.pause_mints_and_burns = pause_mints_and_burns;
+    }
+
+    /// Sets the gas attached to `nft_on_transfer`, for receivers that need more (or less) than
+    /// the default split. `nft_transfer_call` then requires enough prepaid gas to cover it on
+    /// top of the transfer and resolver. Only the contract owner may call it.
+    #[payable]
+    pub fn set_gas_for_on_transfer(&mut self, gas_for_on_transfer: Option<Gas>) {
+        assert_one_yocto();
+        self.assert_owner();
+        if let Some(gas_for_on_transfer) = gas_for_on_transfer {
+            require!(
+                gas_for_on_transfer >= MIN_GAS_FOR_RECEIVER,
+                "gas_for_on_transfer is below the minimum gas for the receiver"
+            );
+        }
+        self.gas_for_on_transfer = gas_for_on_transfer;
+    }
+
+    pub fn gas_for_on_transfer(&self) -> Option<Gas> {
+        self.gas_for_on_transfer
+    }
+
+    /// Sets whether the lock and ownership events record the block they were emitted in.
+    /// Only the contract owner may call it.
+    #[payable]
+    pub fn set_event_block_context(&mut self, event_block_context: bool) {
+        assert_one_yocto();
+        self.assert_owner();
+        self.event_block_context = event_block_context;
+    }
+
+    /// Turns the logging of every event of the contract on or off. Only the contract owner may
+    /// call it.
+    #[payable]
+    pub fn set_emit_events(&mut self, emit_events: bool) {
+        assert_one_yocto();
+        self.assert_owner();
+        self.emit_events = emit_events;
+    }
+
+    /// Sets whether events are logged with the JSON of their `data` encoded in base64, for
+    /// indexers that shouldn't read it. Only the contract owner may call it.
+    #[payable]
+    pub fn set_event_data_base64(&mut self, event_data_base64: bool) {
+        assert_one_yocto();
+        self.assert_owner();
+        self.event_data_base64 = event_data_base64;
+    }
+
+    /// Sets whether `emit_historical_mint` may be called. Only the contract owner may call it.
+    #[payable]
+    pub fn set_migration_mode(&mut self, migration_mode: bool) {
+        assert_one_yocto();
+        self.assert_owner();
+        self.migration_mode = migration_mode;
+    }
+
+    /// Emits a mint event for each token of `token_ids` to its current owner without changing
+    /// any state, for collections that minted them before logging events. Only the contract
+    /// owner may call it, while the migration mode is set.
+    #[payable]
+    pub fn emit_historical_mint(&mut self, token_ids: Vec<TokenId>) {
+        assert_one_yocto();
+        self.assert_owner();
+        require!(
+            self.migration_mode,
+            ContractError::MigrationModeNotSet.message()
+        );
+        require!(!token_ids.is_empty(), "token_ids must not be empty");
+
+        let mut groups: Vec<(AccountId, Vec<&str>)> = Vec::new();
+        for token_id in &token_ids {
+            let owner_id = self
+                .tokens
+                .owner_by_id
+                .get(token_id)
+                .unwrap_or_else(|| ContractError::TokenNotFound.panic());
+            match groups.iter_mut().find(|(owner, _)| owner == &owner_id) {
+                Some((_, token_ids)) => token_ids.push(token_id),
+                None => groups.push((owner_id, vec![token_id])),
+            }
+        }
+
+        // Create a NearEvent
+        self.emit_event(NearEvent::nft_mint(
+            groups
+                .iter()
+                .map(|(owner_id, token_ids)| NftMintData::new(owner_id, token_ids.clone(), None))
+                .collect(),
+        ));
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    /// Records whether the contract account has no access keys left. The contract can't read
+    /// its keys, so this is a statement of the deployer, who calls it from the contract account.
+    #[private]
+    pub fn set_contract_is_locked(&mut self, contract_is_locked: bool) {
+        self.contract_is_locked = contract_is_locked;
+    }
+
+    pub fn contract_is_locked(&self) -> bool {
+        self.contract_is_locked
+    }
+
+    /// Panics unless the deployer recorded that the contract account is locked. Can be called
+    /// before critical operations, or guard them from within the contract.
+    pub fn assert_contract_is_locked(&self) {
+        require!(
+            self.contract_is_locked,
+            ContractError::ContractNotLocked.message()
+        );
+    }
+
+    /// Permanently prevents updates of the contract and token metadata, e.g. after a reveal.
+    /// Only the contract owner may call it, and it can't be undone.
+    #[payable]
+    pub fn freeze_metadata(&mut self) {
+        assert_one_yocto();
+        self.assert_owner();
+        self.metadata_frozen = true;
+    }
+
+    pub fn is_metadata_frozen(&self) -> bool {
+        self.metadata_frozen
+    }
+
+    /// Replaces the contract metadata. Only the contract owner may call it.
+    #[payable]
+    pub fn update_contract_metadata(&mut self, metadata: NFTContractMetadata) {
+        assert_one_yocto();
+        self.assert_owner();
+        self.assert_metadata_not_frozen();
+        validate_contract_metadata(&metadata);
+        self.metadata.set(&metadata);
+
+        // Create a NearEvent
+        self.emit_event(NearEvent::nft_contract_metadata_update(vec![
+            NftContractMetadataUpdateData::new(None),
+        ]));
+    }
+
+    /// Replaces the metadata of `token_id`. Only the contract owner may call it, attaching
+    /// exactly 1 yoctoNEAR; like the contract metadata, the storage is paid by the contract.
+    #[payable]
+    pub fn nft_update_metadata(&mut self, token_id: TokenId, token_metadata: TokenMetadata) {
+        assert_one_yocto();
+        self.assert_owner();
+        self.assert_metadata_not_frozen();
+        require!(
+            self.tokens.owner_by_id.contains_key(&token_id),
+            ContractError::TokenNotFound.message()
+        );
+        let token_metadata_by_id = self
+            .tokens
+            .token_metadata_by_id
+            .as_mut()
+            .unwrap_or_else(|| ContractError::MetadataNotSupported.panic());
+        assert_valid_reference_hash(&token_metadata);
+        token_metadata_by_id.insert(&token_id, &token_metadata);
+
+        // Create a NearEvent
+        self.emit_event(NearEvent::nft_metadata_update(vec![
+            NftMetadataUpdateData::new(vec![&token_id], None),
+        ]));
+    }
+
+    /// Same as `nft_transfer`, but returns the token as it is after the transfer, i.e. owned by
+    /// `receiver_id` with its approvals cleared.
+    #[payable]
+    pub fn nft_transfer_ret(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+    ) -> Token {
+        self.nft_transfer(receiver_id, token_id.clone(), approval_id, memo);
+        self.nft_token(token_id)
+            .unwrap_or_else(|| ContractError::TokenNotFound.panic())
+    }
+
+    /// Transfers `token_id` of `owner_id` to `receiver_id` on behalf of the caller, which must
+    /// be approved on the token with `approval_id`. The transfer event names the caller as
+    /// `authorized_id`.
+    #[payable]
+    pub fn nft_transfer_from(
+        &mut self,
+        owner_id: AccountId,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        approval_id: u64,
+        memo: Option<String>,
+    ) {
+        assert_one_yocto();
+        self.assert_not_paused();
+        self.assert_memo_if_required(&memo);
+        let sender_id = env::predecessor_account_id();
+        require!(
+            self.tokens.owner_by_id.get(&token_id).as_ref() == Some(&owner_id),
+            ContractError::OwnerIdMismatch.message()
+        );
+        require!(
+            sender_id != owner_id,
+            ContractError::OwnerMustTransfer.message()
+        );
+        let actual_approval_id = self
+            .current_approval_id(&token_id, &sender_id)
+            .unwrap_or_else(|| ContractError::ApprovalNotFound.panic());
+        require!(
+            actual_approval_id == approval_id,
+            ContractError::ApprovalIdMismatch.message()
+        );
+        self.internal_transfer(
+            &sender_id,
+            &receiver_id,
+            &token_id,
+            Some(approval_id),
+            memo.clone(),
+        );
+
+        // Create a NearEvent
+        self.emit_event(NearEvent::nft_transfer(vec![NftTransferData::new(
+            &owner_id,
+            &receiver_id,
+            vec![&token_id],
+            Some(&sender_id),
+            memo.as_deref(),
+        )]));
+    }
+
+    /// Same as `nft_transfer`, with a JSON `note` of at most 512 bytes, e.g. provenance
+    /// details, recorded as the memo of the transfer event.
+    #[payable]
+    pub fn nft_transfer_with_note(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        approval_id: Option<u64>,
+        note: String,
+    ) {
+        require!(
+            note.len() <= MAX_NOTE_LEN,
+            format!("Note must be at most {} bytes", MAX_NOTE_LEN)
+        );
+        require!(
+            near_sdk::serde_json::from_str::<Value>(&note).is_ok(),
+            "Note must be valid JSON"
+        );
+        self.nft_transfer(receiver_id, token_id, approval_id, Some(note));
+    }
+
+    /// Offers to swap `my_token`, owned by the caller, for `wanted_token`, replacing any earlier
+    /// offer of `my_token`. The owner of `wanted_token` accepts it with `nft_atomic_swap`. The
+    /// offer is dropped once `my_token` is transferred or burned. Like `nft_approve`, it requires
+    /// a deposit covering the storage it adds, which is refunded once the offer is withdrawn or
+    /// dropped by a transfer.
+    #[payable]
+    pub fn nft_offer_swap(&mut self, my_token: TokenId, wanted_token: TokenId) {
+        require!(
+            env::attached_deposit() > 0,
+            ContractError::DepositRequired.message()
+        );
+        let predecessor_id = env::predecessor_account_id();
+        require!(
+            self.tokens.owner_by_id.get(&my_token).as_ref() == Some(&predecessor_id),
+            ContractError::NotTokenOwner.message()
+        );
+        let initial_storage_usage = env::storage_usage();
+        self.swap_offers.insert(&my_token, &wanted_token);
+
+        // Charge the storage of the offer, refunding what a replaced offer used beyond it
+        let storage_usage = env::storage_usage();
+        self.refund_deposit(storage_usage.saturating_sub(initial_storage_usage));
+        refund_storage(
+            &predecessor_id,
+            initial_storage_usage.saturating_sub(storage_usage),
+        );
+    }
+
+    /// Withdraws the swap offer of `my_token`, owned by the caller, refunding its storage.
+    #[payable]
+    pub fn nft_cancel_swap_offer(&mut self, my_token: TokenId) {
+        assert_one_yocto();
+        let predecessor_id = env::predecessor_account_id();
+        require!(
+            self.tokens.owner_by_id.get(&my_token).as_ref() == Some(&predecessor_id),
+            ContractError::NotTokenOwner.message()
+        );
+        let initial_storage_usage = env::storage_usage();
+        require!(
+            self.swap_offers.remove(&my_token).is_some(),
+            ContractError::SwapOfferNotFound.message()
+        );
+        refund_storage(
+            &predecessor_id,
+            initial_storage_usage - env::storage_usage(),
+        );
+    }
+
+    /// Token `token_id` is offered in exchange for, if any.
+    pub fn nft_swap_offer(&self, token_id: TokenId) -> Option<TokenId> {
+        self.swap_offers.get(&token_id)
+    }
+
+    /// Swaps `my_token`, owned by the caller, with `their_token`, owned by `counterparty`, and
+    /// emits a transfer event for each. `counterparty` must have offered `their_token` for
+    /// `my_token` with `nft_offer_swap`, so that neither side can be taken without the other.
+    #[payable]
+    pub fn nft_atomic_swap(
+        &mut self,
+        my_token: TokenId,
+        their_token: TokenId,
+        counterparty: AccountId,
+    ) {
+        assert_one_yocto();
+        self.assert_not_paused();
+        let predecessor_id = env::predecessor_account_id();
+        require!(
+            self.tokens.owner_by_id.get(&my_token).as_ref() == Some(&predecessor_id),
+            ContractError::NotTokenOwner.message()
+        );
+        require!(
+            self.tokens.owner_by_id.get(&their_token).as_ref() == Some(&counterparty),
+            ContractError::CounterpartyNotOwner.message()
+        );
+        require!(
+            self.swap_offers.get(&their_token).as_ref() == Some(&my_token),
+            ContractError::SwapNotOffered.message()
+        );
+
+        // Each owner moves their own token, the counterparty with the consent of their offer
+        self.internal_transfer(&predecessor_id, &counterparty, &my_token, None, None);
+        self.internal_transfer(&counterparty, &predecessor_id, &their_token, None, None);
+
+        // Create a NearEvent for each side of the swap
+        self.emit_event(NearEvent::nft_transfer(vec![NftTransferData::new(
+            &predecessor_id,
+            &counterparty,
+            vec![&my_token],
+            None,
+            None,
+        )]));
+        self.emit_event(NearEvent::nft_transfer(vec![NftTransferData::new(
+            &counterparty,
+            &predecessor_id,
+            vec![&their_token],
+            Some(&predecessor_id),
+            None,
+        )]));
+    }
+
+    /// Performs every `(receiver_id, token_id, approval_id, memo)` transfer of `transfers` and
+    /// emits a single transfer event with one entry per `(old_owner_id, new_owner_id, memo)`.
+    /// Transfers between the same accounts with different memos are kept in separate entries,
+    /// so that no memo is lost.
+    #[payable]
+    pub fn nft_batch_transfer(
+        &mut self,
+        transfers: Vec<(AccountId, TokenId, Option<u64>, Option<String>)>,
+    ) {
+        assert_one_yocto();
+        require!(!transfers.is_empty(), "transfers must not be empty");
+        self.assert_not_paused();
+        let sender_id = env::predecessor_account_id();
+        let mut groups: Vec<(AccountId, AccountId, Option<String>, Vec<TokenId>)> = Vec::new();
+        for (receiver_id, token_id, approval_id, memo) in transfers {
+            self.assert_memo_if_required(&memo);
+            let (old_owner_id, _) = self.internal_transfer(
+                &sender_id,
+                &receiver_id,
+                &token_id,
+                approval_id,
+                memo.clone(),
+            );
+            match groups
+                .iter_mut()
+                .find(|(old_owner, new_owner, group_memo, _)| {
+                    old_owner == &old_owner_id && new_owner == &receiver_id && group_memo == &memo
+                }) {
+                Some((_, _, _, token_ids)) => token_ids.push(token_id),
+                None => groups.push((old_owner_id, receiver_id, memo, vec![token_id])),
+            }
+        }
+
+        // Create a NearEvent
+        self.emit_event(NearEvent::nft_transfer(
+            groups
+                .iter()
+                .map(|(old_owner_id, new_owner_id, memo, token_ids)| {
+                    NftTransferData::new(
+                        old_owner_id,
+                        new_owner_id,
+                        token_ids.iter().map(|token_id| token_id.as_str()).collect(),
+                        Some(&sender_id).filter(|sender_id| *sender_id != old_owner_id),
+                        memo.as_deref(),
+                    )
+                })
+                .collect(),
+        ));
+    }
+
+    /// Burns every token of `token_ids` and emits a single burn event with one entry per
+    /// former owner. The storage released by the whole batch is refunded to the caller.
+    /// An approved account burning a token may pass `approval_ids`, one per token, to make
+    /// sure its approvals haven't changed since it was given them. Locked tokens can't be
+    /// burned until they're unlocked, and soulbound tokens can only be burned by their owner.
+    #[payable]
+    pub fn nft_burn(
+        &mut self,
+        token_ids: Vec<TokenId>,
+        memo: Option<String>,
+        approval_ids: Option<Vec<u64>>,
+    ) {
+        assert_one_yocto();
+        self.assert_mint_and_burn_not_paused();
+        require!(!token_ids.is_empty(), "token_ids must not be empty");
+        if let Some(approval_ids) = &approval_ids {
+            require!(
+                approval_ids.len() == token_ids.len(),
+                "token_ids and approval_ids must have the same length"
+            );
+        }
+        let predecessor_id = env::predecessor_account_id();
+        let initial_storage_usage = env::storage_usage();
+
+        let mut groups: Vec<(AccountId, Vec<&str>)> = Vec::new();
+        for (index, token_id) in token_ids.iter().enumerate() {
+            let approval_id = approval_ids
+                .as_ref()
+                .map(|approval_ids| approval_ids[index]);
+            let owner_id = self.internal_burn(&predecessor_id, token_id, approval_id);
+            match groups.iter_mut().find(|(owner, _)| owner == &owner_id) {
+                Some((_, token_ids)) => token_ids.push(token_id),
+                None => groups.push((owner_id, vec![token_id])),
+            }
+        }
+
+        // Refund the storage released by the burn to the caller
+        let storage_released = initial_storage_usage.saturating_sub(env::storage_usage());
+        refund_storage(&predecessor_id, storage_released);
+
+        // Create a NearEvent
+        self.emit_event(NearEvent::nft_burn(
+            groups
+                .iter()
+                .map(|(owner_id, token_ids)| {
+                    NftBurnData::new(
+                        owner_id,
+                        token_ids.clone(),
+                        Some(&predecessor_id).filter(|predecessor_id| *predecessor_id != owner_id),
+                        memo.as_deref(),
+                    )
+                })
+                .collect(),
+        ));
+    }
+
+    /// Lists the `(standard, version)` pairs of the events this contract emits. NEP-171 events
+    /// introduced after the configured version are reported under the version that added them.
+    /// Standards whose events can no longer be emitted, e.g. `nft_royalty` once royalties are
+    /// frozen, are left out, and so is every standard while `emit_events` is off.
+    pub fn event_standards(&self) -> Vec<(String, String)> {
+        if !self.emit_events {
+            return vec![];
+        }
+        let mut standards: Vec<(String, String)> = [
+            Nep171Version::V1_0_0,
+            Nep171Version::V1_1_0,
+            Nep171Version::V1_2_0,
+        ]
+        .iter()
+        .filter(|version| **version >= self.event_version)
+        .map(|version| ("nep171".to_string(), version.as_str().to_string()))
+        .collect();
+        let approvals_enabled = self.tokens.approvals_by_id.is_some();
+        let others = [
+            (approvals_enabled, "nep178", "1.0.0"),
+            (true, "storage", "1.0.0"),
+            // Only approved accounts can lock a token
+            (
+                approvals_enabled,
+                lock::LOCK_EVENT_STANDARD,
+                lock::LOCK_EVENT_VERSION,
+            ),
+            (true, OWNERSHIP_EVENT_STANDARD, OWNERSHIP_EVENT_VERSION),
+            (
+                true,
+                approval::OPERATOR_EVENT_STANDARD,
+                approval::OPERATOR_EVENT_VERSION,
+            ),
+            (
+                !self.royalties_frozen,
+                royalty::ROYALTY_EVENT_STANDARD,
+                royalty::ROYALTY_EVENT_VERSION,
+            ),
+            (
+                !self.supply_milestones.is_empty(),
+                SUPPLY_EVENT_STANDARD,
+                SUPPLY_EVENT_VERSION,
+            ),
+        ];
+        standards.extend(
+            others
+                .iter()
+                .filter(|(enabled, _, _)| *enabled)
+                .map(|(_, standard, version)| (standard.to_string(), version.to_string())),
+        );
+        standards
+    }
+
+    /// Number of tokens ever minted, including the ones burned since.
+    pub fn nft_total_minted(&self) -> U128 {
+        U128(self.tokens_minted.into())
+    }
+
+    /// Number of tokens burned since the contract was deployed or migrated.
+    pub fn nft_total_burned(&self) -> U128 {
+        U128(self.tokens_burned.into())
+    }
+
+    /// The next id of a sequential collection, i.e. the number of tokens minted so far.
+    pub fn next_token_id(&self) -> TokenId {
+        self.tokens_minted.to_string()
+    }
+
+    /// Returns the exact `EVENT_JSON` log `nft_mint` would emit, without minting anything.
+    pub fn simulate_mint_event(
+        &self,
+        token_ids: Vec<TokenId>,
+        receiver_id: AccountId,
+        memo: Option<String>,
+    ) -> String {
+        NearEvent::nft_mint(vec![NftMintData::new(
+            &receiver_id,
+            token_ids.iter().map(|token_id| token_id.as_str()).collect(),
+            memo.as_deref(),
+        )])
+        .versioned(self.event_version)
+        .to_json_event_string()
+    }
+
+    /// SHA-256 of the borsh-serialized metadata of `token_id`, for caches to detect metadata
+    /// updates without fetching it. Returns `None` if the token or its metadata is missing.
+    pub fn nft_token_metadata_hash(&self, token_id: TokenId) -> Option<Base64VecU8> {
+        let token_metadata = self.tokens.token_metadata_by_id.as_ref()?.get(&token_id)?;
+        let bytes = token_metadata
+            .try_to_vec()
+            .unwrap_or_else(|_| env::panic_str("Failed to serialize token metadata"));
+        Some(env::sha256(&bytes).into())
+    }
+
+    /// Parses the `extra` metadata field of `token_id` as JSON. Returns `None` if the token has
+    /// no `extra` or it isn't valid JSON.
+    pub fn nft_token_extra_json(&self, token_id: TokenId) -> Option<Value> {
+        let token_metadata = self.tokens.token_metadata_by_id.as_ref()?.get(&token_id)?;
+        near_sdk::serde_json::from_str(&token_metadata.extra?).ok()
+    }
+
+    /// Estimates the deposit `nft_mint` requires for a token with `token_metadata`. The estimate
+    /// errs on the high side, and whatever the mint doesn't use is refunded.
+    pub fn nft_mint_storage_cost(&self, token_metadata: TokenMetadata) -> U128 {
+        let metadata_len = token_metadata
+            .try_to_vec()
+            .unwrap_or_else(|_| env::panic_str("Failed to serialize token metadata"))
+            .len() as StorageUsage;
+        let mut storage = metadata_len + MINT_STORAGE_OVERHEAD;
+        if !self.default_royalty.is_empty() {
+            let royalty_len = self
+                .default_royalty
+                .try_to_vec()
+                .unwrap_or_else(|_| env::panic_str("Failed to serialize royalty"))
+                .len() as StorageUsage;
+            storage += royalty_len + ROYALTY_STORAGE_OVERHEAD;
+        }
+        U128(Balance::from(storage) * env::storage_byte_cost())
+    }
+
+    /// Returns up to `limit` existing tokens minted at a block timestamp from `from_ts` included
+    /// to `to_ts` excluded, oldest first. Tokens minted before the mint times were recorded are
+    /// never returned.
+    pub fn nft_tokens_by_mint_time(
+        &self,
+        from_ts: U64,
+        to_ts: U64,
+        limit: Option<u64>,
+    ) -> Vec<Token> {
+        let limit = limit.map_or(usize::MAX, |limit| limit as usize);
+        require!(limit != 0, "Cannot provide limit of 0.");
+        // The log is sorted by timestamp, so search for the first mint of the window
+        let (mut low, mut high) = (0, self.mint_log.len());
+        while low < high {
+            let middle = low + (high - low) / 2;
+            if self.mint_log.get(middle).unwrap().0 < from_ts.0 {
+                low = middle + 1;
+            } else {
+                high = middle;
+            }
+        }
+        (low..self.mint_log.len())
+            .map(|index| self.mint_log.get(index).unwrap())
+            .take_while(|(minted_at, _)| *minted_at < to_ts.0)
+            // Skip burned tokens, and earlier mints of a token id minted again
+            .filter(|(minted_at, token_id)| self.mint_times.get(token_id) == Some(*minted_at))
+            .filter_map(|(_, token_id)| self.nft_token(token_id))
+            .take(limit)
+            .collect()
+    }
+
+    /// Returns whether `token_id` is soulbound, locked or frozen in a single call, or `None` if
+    /// the token doesn't exist.
+    pub fn nft_token_flags(&self, token_id: TokenId) -> Option<TokenFlags> {
+        if !self.tokens.owner_by_id.contains_key(&token_id) {
+            return None;
+        }
+        Some(TokenFlags {
+            soulbound: self.soulbound.contains_key(&token_id),
+            locked_by: self.locks.get(&token_id),
+            frozen: self.metadata_frozen,
+        })
+    }
+
+    /// Returns the `media` and `reference` URLs of `token_id` joined with the contract
+    /// `base_uri`. Absolute URLs are returned unchanged.
+    pub fn nft_token_resolved_urls(&self, token_id: TokenId) -> Option<ResolvedUrls> {
+        let token_metadata = self.tokens.token_metadata_by_id.as_ref()?.get(&token_id)?;
+        let base_uri = self.metadata.get().and_then(|metadata| metadata.base_uri);
+        let resolve = |url: String| match &base_uri {
+            Some(base_uri) if !url.contains("://") && !url.starts_with("data:") => format!(
+                "{}/{}",
+                base_uri.trim_end_matches('/'),
+                url.trim_start_matches('/')
+            ),
+            _ => url,
+        };
+        Some(ResolvedUrls {
+            media: token_metadata.media.map(resolve),
+            reference: token_metadata.reference.map(resolve),
+        })
+    }
+
+    /// Reports the storage the contract holds and what it costs, for reconciling the deposits
+    /// collected for the current tokens. `total_bytes` is the whole contract state, including
+    /// the collection-wide data shared by every token.
+    pub fn nft_storage_report(&self) -> StorageReport {
+        let total_bytes = env::storage_usage();
+        StorageReport {
+            total_tokens: U128(self.tokens.owner_by_id.len().into()),
+            total_bytes: U64(total_bytes),
+            total_cost: U128(Balance::from(total_bytes) * env::storage_byte_cost()),
+        }
+    }
+
+    /// Resolves every token of `token_ids` like `nft_token`, preserving their order.
+    pub fn nft_tokens_batch(&self, token_ids: Vec<TokenId>) -> Vec<Option<Token>> {
+        token_ids
+            .into_iter()
+            .map(|token_id| self.nft_token(token_id))
+            .collect()
+    }
+
+    /// Returns how many tokens `account_id` owns, like `nft_supply_for_owner` but without
+    /// loading the owner's token set once it has a cached balance.
+    pub fn nft_balance(&self, account_id: AccountId) -> U128 {
+        U128(self.internal_balance(&account_id).into())
+    }
+
+    /// Returns a page of the tokens owned by `account_id` along with how many tokens it owns
+    /// in total, saving callers a separate `nft_supply_for_owner` call.
+    pub fn nft_tokens_for_owner_paged(
+        &self,
+        account_id: AccountId,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> (Vec<Token>, U128) {
+        let supply = self.tokens.nft_supply_for_owner(account_id.clone());
+        let tokens = self
+            .tokens
+            .nft_tokens_for_owner(account_id, from_index, limit);
+        (tokens, supply)
+    }
+
+    /// Paginates over the tokens like `nft_tokens`, returning only their ids and metadata for
+    /// views that don't need owners or approvals.
+    pub fn nft_tokens_metadata(
+        &self,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<(TokenId, TokenMetadata)> {
+        let start_index: u128 = from_index.map(From::from).unwrap_or_default();
+        require!(
+            u128::from(self.tokens.owner_by_id.len()) >= start_index,
+            "Out of bounds, please use a smaller from_index."
+        );
+        let limit = limit.map_or(usize::MAX, |limit| limit as usize);
+        require!(limit != 0, "Cannot provide limit of 0.");
+        let token_metadata_by_id = self
+            .tokens
+            .token_metadata_by_id
+            .as_ref()
+            .unwrap_or_else(|| ContractError::MetadataNotSupported.panic());
+        self.tokens
+            .owner_by_id
+            .iter()
+            .skip(start_index as usize)
+            .take(limit)
+            .filter_map(|(token_id, _)| {
+                let metadata = token_metadata_by_id.get(&token_id)?;
+                Some((token_id, metadata))
+            })
+            .collect()
+    }
+
+    /// Returns the number of tokens of `account_id` along with up to `limit` of their ids,
+    /// 10 by default, so wallets can show both in one call. Unknown owners get an empty summary.
+    pub fn nft_owner_summary(&self, account_id: AccountId, limit: Option<u64>) -> OwnerSummary {
+        let limit = limit.map_or(OWNER_SUMMARY_DEFAULT_LIMIT, |limit| limit as usize);
+        let token_ids = self
+            .tokens
+            .tokens_per_owner
+            .as_ref()
+            .and_then(|tokens_per_owner| tokens_per_owner.get(&account_id));
+        match token_ids {
+            Some(token_ids) => OwnerSummary {
+                count: U128(token_ids.len().into()),
+                token_ids: token_ids.iter().take(limit).collect(),
+            },
+            None => OwnerSummary {
+                count: U128(0),
+                token_ids: vec![],
+            },
+        }
+    }
+
+    /// Returns up to `limit` tokens of `account_id` in token id order, starting after
+    /// `start_token_id`. Unlike index-based pages, passing the last token id of a page as the
+    /// cursor of the next one is stable while tokens are minted or transferred. Reads every
+    /// token id of the owner, so very large wallets may run out of gas.
+    pub fn nft_tokens_for_owner_from(
+        &self,
+        account_id: AccountId,
+        start_token_id: Option<TokenId>,
+        limit: Option<u64>,
+    ) -> Vec<Token> {
+        let tokens_per_owner = self
+            .tokens
+            .tokens_per_owner
+            .as_ref()
+            .unwrap_or_else(|| env::panic_str("Could not find tokens_per_owner"));
+        let mut token_ids = match tokens_per_owner.get(&account_id) {
+            Some(token_ids) => token_ids.to_vec(),
+            None => return vec![],
+        };
+        token_ids.sort();
+        let limit = limit.map_or(usize::MAX, |limit| limit as usize);
+        require!(limit != 0, "Cannot provide limit of 0.");
+        token_ids
+            .into_iter()
+            .skip_while(|token_id| matches!(&start_token_id, Some(start) if token_id <= start))
+            .take(limit)
+            .filter_map(|token_id| self.nft_token(token_id))
+            .collect()
+    }
+}
+
+impl Contract {
+    /// Builds the contract around existing token and metadata storage, every other field
+    /// starting empty.
+    fn from_parts(tokens: NonFungibleToken, metadata: LazyOption<NFTContractMetadata>) -> Self {
+        let tokens_minted = tokens.owner_by_id.len();
+        Self {
+            tokens,
+            metadata,
+            royalties: LookupMap::new(StorageKey::Royalties),
+            minters: UnorderedSet::new(StorageKey::Minters),
+            paused: false,
+            pause_mints_and_burns: false,
+            pending_owner: None,
+            soulbound: LookupMap::new(StorageKey::Soulbound),
+            locks: LookupMap::new(StorageKey::Locks),
+            approval_expiries: LookupMap::new(StorageKey::ApprovalExpiries),
+            balances: LookupMap::new(StorageKey::Balances),
+            event_version: Nep171Version::default(),
+            gas_for_on_transfer: None,
+            tokens_minted,
+            contract_is_locked: false,
+            max_supply: None,
+            operator_approvals: LookupMap::new(StorageKey::OperatorApprovals),
+            metadata_frozen: false,
+            allowed_receivers: UnorderedSet::new(StorageKey::AllowedReceivers),
+            transfers_in_flight: LookupMap::new(StorageKey::TransfersInFlight),
+            default_royalty: HashMap::new(),
+            event_block_context: false,
+            mint_requests: LookupMap::new(StorageKey::MintRequests),
+            mint_request_ids: Vector::new(StorageKey::MintRequestIds),
+            mint_requests_recorded: 0,
+            require_memo: false,
+            supply_milestones: Vec::new(),
+            tokens_burned: 0,
+            emit_events: true,
+            token_owner_updates_royalty: false,
+            royalties_frozen: false,
+            mint_times: LookupMap::new(StorageKey::MintTimes),
+            mint_log: Vector::new(StorageKey::MintLog),
+            event_data_base64: false,
+            swap_offers: LookupMap::new(StorageKey::SwapOffers),
+            migration_mode: false,
+        }
+    }
+
+    fn assert_owner(&self) {
+        require!(
+            env::predecessor_account_id() == self.tokens.owner_id,
+            ContractError::NotAuthorized.message()
+        );
+    }
+
+    /// Charges the attached deposit for `storage_used` bytes and refunds the rest to the caller.
+    /// An excess of exactly 1 yoctoNEAR, the deposit attached to require a full access key,
+    /// isn't refunded.
+    fn refund_deposit(&self, storage_used: StorageUsage) {
+        let required_cost = env::storage_byte_cost() * Balance::from(storage_used);
+        let attached_deposit = env::attached_deposit();
+        if required_cost > attached_deposit {
+            env::panic_str(&format!(
+                "Must attach {} yoctoNEAR to cover storage, {} more than attached",
+                required_cost,
+                required_cost - attached_deposit
+            ));
+        }
+        let refund = attached_deposit - required_cost;
+        if refund > 1 {
+            let predecessor_id = env::predecessor_account_id();
+            self.emit_event(NearEvent::storage_refund(vec![StorageRefundData::new(
+                &predecessor_id,
+                refund,
+            )]));
+            Promise::new(predecessor_id).transfer(refund);
+        }
+    }
+
+    /// Emits `event` under the NEP-171 version selected at init, unless `emit_events` is
+    /// turned off.
+    fn emit_event(&self, event: NearEvent) {
+        if !self.emit_events {
+            return;
+        }
+        let event = event.versioned(self.event_version);
+        let log = if self.event_data_base64 {
+            event.to_base64_data_event_string()
+        } else {
+            event.to_json_event_string()
+        };
+        event::ContractSink.log(&log);
+    }
+
+    /// Emits `event`, with the current block as context if `event_block_context` is set,
+    /// unless `emit_events` is turned off.
+    fn emit_generic_event(&self, event: GenericEvent) {
+        if !self.emit_events {
+            return;
+        }
+        let event = if self.event_block_context {
+            event.with_block_context()
+        } else {
+            event
+        };
+        let log = if self.event_data_base64 {
+            event.to_base64_data_event_string()
+        } else {
+            event.to_json_event_string()
+        };
+        event::ContractSink.log(&log);
+    }
+
+    /// Emits a `supply_milestone` event for each milestone the total supply reached, which is
+    /// then forgotten so that burning and minting again doesn't report it twice.
+    fn emit_reached_supply_milestones(&mut self) {
+        let total_supply = self.tokens.owner_by_id.len();
+        let reached = self
+            .supply_milestones
+            .iter()
+            .take_while(|milestone| **milestone <= total_supply)
+            .count();
+        for milestone in self.supply_milestones.drain(..reached).collect::<Vec<_>>() {
+            self.emit_generic_event(GenericEvent::new(
+                SUPPLY_EVENT_STANDARD,
+                SUPPLY_EVENT_VERSION,
+                "supply_milestone",
+                json!([{ "milestone": U64(milestone), "total_supply": U64(total_supply) }]),
+            ));
+        }
+    }
+
+    fn assert_metadata_not_frozen(&self) {
+        require!(
+            !self.metadata_frozen,
+            ContractError::MetadataFrozen.message()
+        );
+    }
+
+    /// The contract owner is always allowed to mint, on top of the `minters` allowlist.
+    fn assert_minter(&self) {
+        let predecessor_id = env::predecessor_account_id();
+        require!(
+            predecessor_id == self.tokens.owner_id || self.minters.contains(&predecessor_id),
+            ContractError::NotAuthorizedMinter.message()
+        );
+    }
+
+    fn assert_not_paused(&self) {
+        require!(!self.paused, ContractError::Paused.message());
+    }
+
+    fn assert_memo_if_required(&self, memo: &Option<String>) {
+        require!(
+            !self.require_memo || memo.is_some(),
+            ContractError::MemoRequired.message()
+        );
+    }
+
+    fn assert_mint_and_burn_not_paused(&self) {
+        require!(
+            !(self.paused && self.pause_mints_and_burns),
+            ContractError::Paused.message()
+        );
+    }
+
+    /// Transfers `token_id` like `NonFungibleToken::internal_transfer`, after checking the
+    /// restrictions this contract puts on top of the standard.
+    fn internal_transfer(
+        &mut self,
+        sender_id: &AccountId,
+        receiver_id: &AccountId,
+        token_id: &TokenId,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+    ) -> (AccountId, Option<HashMap<AccountId, u64>>) {
+        assert_valid_token_id(token_id);
+        require!(
+            !self.soulbound.contains_key(token_id),
+            ContractError::TokenSoulbound.message()
+        );
+        require!(
+            !self.transfers_in_flight.contains_key(token_id),
+            ContractError::TransferInProgress.message()
+        );
+        let initial_storage_usage = env::storage_usage();
+        if let Some(locked_by) = self.locks.remove(token_id) {
+            require!(
+                &locked_by == sender_id,
+                ContractError::TokenLocked.message()
+            );
+            // The transfer uses up the lock, so its storage goes back to the lock holder
+            refund_storage(&locked_by, initial_storage_usage - env::storage_usage());
+        }
+        self.assert_approval_not_expired(token_id, sender_id);
+        let owner_id = self
+            .tokens
+            .owner_by_id
+            .get(token_id)
+            .unwrap_or_else(|| ContractError::TokenNotFound.panic());
+        require!(
+            &owner_id != receiver_id,
+            ContractError::SelfTransfer.message()
+        );
+        // An operator of the owner transfers on its behalf, without a per-token approval
+        let (authorized_id, approval_id) = if sender_id != &owner_id
+            && self.is_approved_for_all(owner_id.clone(), sender_id.clone())
+        {
+            (&owner_id, None)
+        } else {
+            (sender_id, approval_id)
+        };
+        let (old_owner_id, old_approvals) =
+            self.tokens
+                .internal_transfer(authorized_id, receiver_id, token_id, approval_id, memo);
+        // The transfer drops the approvals, and so their expiries. Expiring approvals aren't
+        // returned, so that a refused `nft_transfer_call` doesn't restore them without expiry.
+        let old_approvals = old_approvals.map(|mut approvals| {
+            approvals.retain(|approved_account_id, _| {
+                self.approval_expiries
+                    .remove(&(token_id.clone(), approved_account_id.clone()))
+                    .is_none()
+            });
+            approvals
+        });
+        let storage_usage = env::storage_usage();
+        if self.swap_offers.remove(token_id).is_some() {
+            // The offer is dropped with the transfer, so its storage goes back to the old owner
+            refund_storage(&old_owner_id, storage_usage - env::storage_usage());
+        }
+        self.decrement_balance(&old_owner_id);
+        self.increment_balance(receiver_id);
+        #[cfg(test)]
+        self.assert_counter_invariants();
+        (old_owner_id, old_approvals)
+    }
+
+    /// Remembers that `request_id` minted `token_id`, forgetting the oldest request once
+    /// `MAX_MINT_REQUESTS` are remembered.
+    fn record_mint_request(&mut self, request_id: String, token_id: &TokenId) {
+        let index = self.mint_requests_recorded % MAX_MINT_REQUESTS;
+        if index < self.mint_request_ids.len() {
+            let oldest_request_id = self.mint_request_ids.replace(index, &request_id);
+            self.mint_requests.remove(&oldest_request_id);
+        } else {
+            self.mint_request_ids.push(&request_id);
+        }
+        self.mint_requests.insert(&request_id, token_id);
+        self.mint_requests_recorded += 1;
+    }
+
+    /// Number of tokens owned by `account_id`. Owners without a cached balance, such as those
+    /// of tokens minted before `migrate`, are counted from `tokens_per_owner`.
+    pub(crate) fn internal_balance(&self, account_id: &AccountId) -> u64 {
+        self.balances
+            .get(account_id)
+            .unwrap_or_else(|| self.enumerated_balance(account_id))
+    }
+
+    fn enumerated_balance(&self, account_id: &AccountId) -> u64 {
+        self.tokens
+            .tokens_per_owner
+            .as_ref()
+            .and_then(|tokens_per_owner| tokens_per_owner.get(account_id))
+            .map_or(0, |token_ids| token_ids.len())
+    }
+
+    /// Counts one more token for `account_id`. Called once `tokens_per_owner` is updated, so
+    /// that it already counts the token for owners without a cached balance.
+    fn increment_balance(&mut self, account_id: &AccountId) {
+        let balance = match self.balances.get(account_id) {
+            Some(balance) => balance + 1,
+            None => self.enumerated_balance(account_id),
+        };
+        self.balances.insert(account_id, &balance);
+    }
+
+    /// Counts one less token for `account_id`, see `increment_balance`.
+    fn decrement_balance(&mut self, account_id: &AccountId) {
+        let balance = match self.balances.get(account_id) {
+            Some(balance) => balance.saturating_sub(1),
+            None => self.enumerated_balance(account_id),
+        };
+        if balance == 0 {
+            self.balances.remove(account_id);
+        } else {
+            self.balances.insert(account_id, &balance);
+        }
+    }
+
+    /// Records `token_id` as owned by `owner_id` without touching the attached deposit,
+    /// so that callers can mint several tokens before charging for their storage. Callers
+    /// emit the mint event, then `emit_reached_supply_milestones`.
+    fn internal_mint_without_refund(
+        &mut self,
+        token_id: TokenId,
+        owner_id: AccountId,
+        token_metadata: TokenMetadata,
+    ) -> Token {
+        assert_valid_token_id(&token_id);
+        assert_valid_reference_hash(&token_metadata);
+        if let Some(max_supply) = self.max_supply {
+            require!(
+                self.tokens_minted < max_supply,
+                ContractError::MaxSupplyReached.message()
+            );
+        }
+        require!(
+            self.tokens.owner_by_id.get(&token_id).is_none(),
+            ContractError::TokenAlreadyExists.message()
+        );
+        self.tokens.owner_by_id.insert(&token_id, &owner_id);
+        if !self.default_royalty.is_empty() {
+            self.royalties.insert(&token_id, &self.default_royalty);
+        }
+        if let Some(token_metadata_by_id) = &mut self.tokens.token_metadata_by_id {
+            token_metadata_by_id.insert(&token_id, &token_metadata);
+        }
+        if let Some(tokens_per_owner) = &mut self.tokens.tokens_per_owner {
+            let mut token_ids = tokens_per_owner.get(&owner_id).unwrap_or_else(|| {
+                UnorderedSet::new(StorageKey::TokensPerOwner {
+                    account_hash: env::sha256(owner_id.as_bytes()),
+                })
+            });
+            token_ids.insert(&token_id);
+            tokens_per_owner.insert(&owner_id, &token_ids);
+        }
+        self.increment_balance(&owner_id);
+        let minted_at = env::block_timestamp();
+        self.mint_times.insert(&token_id, &minted_at);
+        self.mint_log.push(&(minted_at, token_id.clone()));
+        self.tokens_minted += 1;
+        #[cfg(test)]
+        self.assert_counter_invariants();
+        let approved_account_ids = self.tokens.approvals_by_id.as_ref().map(|_| HashMap::new());
+        Token {
+            token_id,
+            owner_id,
+            metadata: Some(token_metadata),
+            approved_account_ids,
+        }
+    }
+
+    /// Removes every trie entry of `token_id`, including its approvals and royalties, and
+    /// returns its former owner.
+    /// Panics unless `sender_id` is the owner or an approved account of the token, with
+    /// `approval_id` as its approval id when given.
+    fn internal_burn(
+        &mut self,
+        sender_id: &AccountId,
+        token_id: &TokenId,
+        approval_id: Option<u64>,
+    ) -> AccountId {
+        assert_valid_token_id(token_id);
+        self.assert_approval_not_expired(token_id, sender_id);
+        let owner_id = self
+            .tokens
+            .owner_by_id
+            .get(token_id)
+            .unwrap_or_else(|| ContractError::TokenNotFound.panic());
+        require!(
+            !self.locks.contains_key(token_id),
+            ContractError::TokenLocked.message()
+        );
+        require!(
+            sender_id == &owner_id || !self.soulbound.contains_key(token_id),
+            ContractError::NotSoulboundOwner.message()
+        );
+        let approved_account_ids = self
+            .tokens
+            .approvals_by_id
+            .as_mut()
+            .and_then(|by_id| by_id.remove(token_id));
+        if sender_id != &owner_id {
+            let actual_approval_id = approved_account_ids
+                .as_ref()
+                .and_then(|approvals| approvals.get(sender_id));
+            require!(
+                actual_approval_id.is_some(),
+                ContractError::NotAuthorized.message()
+            );
+            if let Some(approval_id) = approval_id {
+                require!(
+                    actual_approval_id == Some(&approval_id),
+                    ContractError::ApprovalIdMismatch.message()
+                );
+            }
+        }
+        self.remove_approval_expiries(
+            token_id,
+            approved_account_ids.iter().flat_map(HashMap::keys),
+        );
+
+        self.tokens.owner_by_id.remove(token_id);
+        self.royalties.remove(token_id);
+        self.soulbound.remove(token_id);
+        self.locks.remove(token_id);
+        self.swap_offers.remove(token_id);
+        if let Some(next_approval_id_by_id) = &mut self.tokens.next_approval_id_by_id {
+            next_approval_id_by_id.remove(token_id);
+        }
+        if let Some(token_metadata_by_id) = &mut self.tokens.token_metadata_by_id {
+            token_metadata_by_id.remove(token_id);
+        }
+        if let Some(tokens_per_owner) = &mut self.tokens.tokens_per_owner {
+            let mut owner_tokens = tokens_per_owner
+                .get(&owner_id)
+                .unwrap_or_else(|| env::panic_str("Unable to access tokens per owner"));
+            owner_tokens.remove(token_id);
+            if owner_tokens.is_empty() {
+                tokens_per_owner.remove(&owner_id);
+            } else {
+                tokens_per_owner.insert(&owner_id, &owner_tokens);
+            }
+        }
+        self.decrement_balance(&owner_id);
+        self.mint_times.remove(token_id);
+        self.tokens_burned += 1;
+        #[cfg(test)]
+        self.assert_counter_invariants();
+        owner_id
+    }
+
+    /// Panics unless the cached balance of every owner matches its number of tokens in the
+    /// enumeration. Walks every token, so it only runs in unit tests, after every change of
+    /// ownership.
+    #[cfg(test)]
+    fn assert_counter_invariants(&self) {
+        let mut supplies: HashMap<AccountId, u64> = HashMap::new();
+        for (_, owner_id) in self.tokens.owner_by_id.iter() {
+            *supplies.entry(owner_id).or_default() += 1;
+        }
+        for (owner_id, supply) in supplies {
+            let balance = self.internal_balance(&owner_id);
+            let enumerated = self
+                .tokens
+                .tokens_per_owner
+                .as_ref()
+                .and_then(|tokens_per_owner| tokens_per_owner.get(&owner_id))
+                .map_or(0, |token_ids| token_ids.len());
+            if balance != supply || enumerated != supply {
+                env::panic_str(&format!(
+                    "{} owns {} tokens, but its balance is {} and it enumerates {}",
+                    owner_id, supply, balance, enumerated
+                ));
+            }
+        }
+        require!(
+            self.tokens_minted >= self.tokens.owner_by_id.len(),
+            "Fewer tokens minted than in supply"
+        );
+    }
+}
+
+const MAX_TOKEN_ID_LEN: usize = 256;
+
+const MAX_NOTE_LEN: usize = 512;
+
+/// Checks that `token_id` is non-empty, at most 256 bytes long and free of control
+/// characters, the rules every method taking a token id applies.
+///
+/// ```
+/// use rust_contract_events::{validate_token_id, ContractError};
+///
+/// assert_eq!(validate_token_id("token-1"), Ok(()));
+/// assert_eq!(validate_token_id(""), Err(ContractError::TokenIdEmpty));
+/// assert_eq!(validate_token_id("a\nb"), Err(ContractError::TokenIdInvalidChars));
+/// ```
+pub fn validate_token_id(token_id: &str) -> Result<(), ContractError> {
+    if token_id.is_empty() {
+        return Err(ContractError::TokenIdEmpty);
+    }
+    if token_id.len() > MAX_TOKEN_ID_LEN {
+        return Err(ContractError::TokenIdTooLong);
+    }
+    if token_id.chars().any(char::is_control) {
+        return Err(ContractError::TokenIdInvalidChars);
+    }
+    Ok(())
+}
+
+fn assert_valid_token_id(token_id: &str) {
+    if let Err(error) = validate_token_id(token_id) {
+        error.panic();
+    }
+}
+
+/// Checks that `reference_hash`, when present, is a base64-encoded SHA-256 hash. The base64
+/// encoding itself is already checked when deserializing the metadata.
+fn assert_valid_reference_hash(token_metadata: &TokenMetadata) {
+    if let Some(reference_hash) = &token_metadata.reference_hash {
+        require!(
+            reference_hash.0.len() == 32,
+            "reference_hash must be a 32-byte SHA-256 hash"
+        );
+    }
+}
+
+const MAX_DATA_URI_ICON_LEN: usize = 64 * 1024;
+
+/// Checks `metadata` like `NFTContractMetadata::assert_valid`, and additionally that
+/// `base_uri` is an HTTPS or IPFS URL and that a data URI icon is at most 64 KiB.
+fn validate_contract_metadata(metadata: &NFTContractMetadata) {
+    metadata.assert_valid();
+    if let Some(base_uri) = &metadata.base_uri {
+        require!(
+            base_uri.starts_with("https://") || base_uri.starts_with("ipfs://"),
+            "base_uri must be an https:// or ipfs:// URL"
+        );
+    }
+    if let Some(icon) = &metadata.icon {
+        require!(
+            !icon.starts_with("data:") || icon.len() <= MAX_DATA_URI_ICON_LEN,
+            format!(
+                "Data URI icon must be at most {} bytes",
+                MAX_DATA_URI_ICON_LEN
+            )
+        );
+    }
+}
+
+/// Sends the storage cost of the `approved_account_ids` entries back to `account_id`.
+fn refund_approved_account_ids(
+    account_id: &AccountId,
+    approved_account_ids: &HashMap<AccountId, u64>,
+) {
+    let storage_released = approved_account_ids
+        .keys()
+        .map(|approved_account_id| {
+            // The extra 4 bytes store the length of the borsh-serialized account id
+            approved_account_id.as_str().len() as StorageUsage
+                + 4
+                + std::mem::size_of::<u64>() as StorageUsage
+        })
+        .sum();
+    refund_storage(account_id, storage_released);
+}
+
+/// Sends the cost of `storage_released` bytes back to `account_id`.
+fn refund_storage(account_id: &AccountId, storage_released: StorageUsage) {
+    let refund = env::storage_byte_cost() * Balance::from(storage_released);
+    if refund > 0 {
+        Promise::new(account_id.clone()).transfer(refund);
+    }
+}
+
+near_contract_standards::impl_non_fungible_token_enumeration!(Contract, tokens);
+
+#[near_bindgen]
+impl NonFungibleTokenMetadataProvider for Contract {
+    fn nft_metadata(&self) -> NFTContractMetadata {
+        self.metadata.get().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_contract_standards::non_fungible_token::approval::NonFungibleTokenApproval;
+    use near_contract_standards::non_fungible_token::enumeration::NonFungibleTokenEnumeration;
+    use near_sdk::json_types::{Base64VecU8, U128};
+    use near_sdk::serde_json::json;
+    use near_sdk::test_utils::{accounts, VMContextBuilder};
+    use near_sdk::testing_env;
+
+    /// More than any test mint takes, the excess being refunded.
+    const MINT_DEPOSIT: Balance = 100_000_000_000_000_000_000_000;
+
+    fn contract_id() -> AccountId {
+        "nft.near".parse().unwrap()
+    }
+
+    fn get_context(predecessor_account_id: AccountId) -> VMContextBuilder {
+        let mut builder = VMContextBuilder::new();
+        builder
+            .current_account_id(contract_id())
+            .signer_account_id(predecessor_account_id.clone())
+            .predecessor_account_id(predecessor_account_id);
+        builder
+    }
+
+    /// Sets the caller and attached deposit of the next call, which also clears the logs.
+    fn set_caller(context: &mut VMContextBuilder, account_id: AccountId, deposit: Balance) {
+        testing_env!(context
+            .signer_account_id(account_id.clone())
+            .predecessor_account_id(account_id)
+            .attached_deposit(deposit)
+            .build());
+    }
+
+    fn sample_token_metadata() -> TokenMetadata {
+        TokenMetadata {
+            title: Some("Olympus Mons".into()),
+            description: Some("The tallest mountain in the charted solar system".into()),
+            media: None,
+            media_hash: None,
+            copies: Some(1u64),
+            issued_at: None,
+            expires_at: None,
+            starts_at: None,
+            updated_at: None,
+            extra: None,
+            reference: None,
+            reference_hash: None,
+        }
+    }
+
+    /// Deploys the contract owned by `accounts(0)`.
+    fn setup() -> (VMContextBuilder, Contract) {
+        let context = get_context(accounts(0));
+        testing_env!(context.build());
+        let contract = Contract::new_default_meta(accounts(0));
+        (context, contract)
+    }
+
+    /// Mints `token_id` to `receiver_id` as the contract owner.
+    fn mint(
+        context: &mut VMContextBuilder,
+        contract: &mut Contract,
+        token_id: &str,
+        receiver_id: AccountId,
+    ) -> Token {
+        set_caller(context, accounts(0), MINT_DEPOSIT);
+        contract.nft_mint(
+            token_id.to_string(),
+            receiver_id,
+            sample_token_metadata(),
+            None,
+            None,
+            None,
+        )
+    }
+
+    /// Approves `account_id` for `token_id` as its owner `owner_id`, returning the approval id.
+    fn approve(
+        context: &mut VMContextBuilder,
+        contract: &mut Contract,
+        token_id: &str,
+        owner_id: AccountId,
+        account_id: AccountId,
+    ) -> u64 {
+        set_caller(context, owner_id, MINT_DEPOSIT);
+        contract.nft_approve(token_id.to_string(), account_id.clone(), None);
+        contract
+            .current_approval_id(&token_id.to_string(), &account_id)
+            .unwrap()
+    }
+
+    /// Sets `result` as the outcome of the receiver call resolved by the next call, made by the
+    /// contract itself.
+    fn set_promise_result(context: &mut VMContextBuilder, result: PromiseResult) {
+        testing_env!(
+            context
+                .signer_account_id(contract_id())
+                .predecessor_account_id(contract_id())
+                .attached_deposit(0)
+                .build(),
+            near_sdk::VMConfig::test(),
+            near_sdk::RuntimeFeesConfig::test(),
+            Default::default(),
+            vec![result]
+        );
+    }
+
+    /// Sums the deposits transferred to `account_id` by the last call.
+    fn transferred_to(account_id: &AccountId) -> Balance {
+        near_sdk::test_utils::get_created_receipts()
+            .iter()
+            .filter(|receipt| &receipt.receiver_id == account_id)
+            .flat_map(|receipt| &receipt.actions)
+            .map(|action| match action {
+                near_sdk::mock::VmAction::Transfer { deposit } => *deposit,
+                _ => 0,
+            })
+            .sum()
+    }
+
+    #[test]
+    fn burn_removes_token() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        mint(&mut context, &mut contract, "1", accounts(1));
+
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_burn(vec!["0".to_string()], None, None);
+
+        assert!(contract.nft_token("0".to_string()).is_none());
+        assert_eq!(contract.nft_total_supply(), U128(1));
+        assert_eq!(contract.nft_supply_for_owner(accounts(1)), U128(1));
+    }
+
+    #[test]
+    fn transfer_event_reports_previous_owner() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_transfer(accounts(2), "0".to_string(), None, None);
+
+        let event = event::last_event();
+        assert_eq!(event["event"], "nft_transfer");
+        assert_eq!(event["data"][0]["old_owner_id"], accounts(1).as_str());
+        assert_eq!(event["data"][0]["new_owner_id"], accounts(2).as_str());
+    }
+
+    #[test]
+    fn transfer_call_by_approved_account_emits_transfer() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        let approval_id = approve(&mut context, &mut contract, "0", accounts(1), accounts(2));
+
+        set_caller(&mut context, accounts(2), 1);
+        contract.nft_transfer_call(
+            accounts(3),
+            "0".to_string(),
+            Some(approval_id),
+            None,
+            "".to_string(),
+        );
+
+        let event = event::last_event();
+        assert_eq!(event["event"], "nft_transfer");
+        assert_eq!(event["data"][0]["old_owner_id"], accounts(1).as_str());
+        assert_eq!(event["data"][0]["new_owner_id"], accounts(3).as_str());
+        assert_eq!(event["data"][0]["authorized_id"], accounts(2).as_str());
+    }
+
+    #[test]
+    fn resolve_transfer_rollback_emits_reverse_transfer() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_transfer_call(accounts(2), "0".to_string(), None, None, "".to_string());
+
+        // The receiver asks for the token to be returned
+        set_promise_result(&mut context, PromiseResult::Successful(b"true".to_vec()));
+        let kept = contract.nft_resolve_transfer(
+            accounts(1),
+            accounts(2),
+            "0".to_string(),
+            Some(HashMap::new()),
+        );
+
+        assert!(!kept);
+        assert_eq!(
+            contract.nft_token("0".to_string()).unwrap().owner_id,
+            accounts(1)
+        );
+        let event = event::last_event();
+        assert_eq!(event["event"], "nft_transfer");
+        assert_eq!(event["data"][0]["old_owner_id"], accounts(2).as_str());
+        assert_eq!(event["data"][0]["new_owner_id"], accounts(1).as_str());
+    }
+
+    #[test]
+    fn approve_event_json() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        approve(&mut context, &mut contract, "0", accounts(1), accounts(2));
+
+        assert_eq!(
+            event::last_event(),
+            json!({
+                "standard": "nep178",
+                "version": "1.0.0",
+                "event": "nft_approve",
+                "data": [
+                    {
+                        "owner_id": "bob",
+                        "token_id": "0",
+                        "approved_account_id": "charlie",
+                        "approval_id": 1
+                    }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn batch_mint_emits_one_event() {
+        let (mut context, mut contract) = setup();
+        set_caller(&mut context, accounts(0), MINT_DEPOSIT);
+        contract.nft_batch_mint(
+            vec!["0".to_string(), "1".to_string()],
+            accounts(1),
+            vec![sample_token_metadata(), sample_token_metadata()],
+        );
+
+        let events: Vec<_> = event::logged_events()
+            .into_iter()
+            .filter(|event| event["event"] == "nft_mint")
+            .collect();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["data"].as_array().unwrap().len(), 1);
+        assert_eq!(events[0]["data"][0]["owner_id"], accounts(1).as_str());
+        assert_eq!(
+            events[0]["data"][0]["token_ids"],
+            near_sdk::serde_json::json!(["0", "1"])
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "token_ids must not be empty")]
+    fn batch_mint_rejects_empty_batch() {
+        let (mut context, mut contract) = setup();
+        set_caller(&mut context, accounts(0), MINT_DEPOSIT);
+        contract.nft_batch_mint(vec![], accounts(1), vec![]);
+    }
+
+    #[test]
+    fn batch_transfer_groups_tokens_by_owners() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        mint(&mut context, &mut contract, "1", accounts(1));
+
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_batch_transfer(vec![
+            (accounts(2), "0".to_string(), None, None),
+            (accounts(2), "1".to_string(), None, None),
+        ]);
+
+        let event = event::last_event();
+        assert_eq!(event["event"], "nft_transfer");
+        assert_eq!(event["data"].as_array().unwrap().len(), 1);
+        assert_eq!(event["data"][0]["old_owner_id"], accounts(1).as_str());
+        assert_eq!(event["data"][0]["new_owner_id"], accounts(2).as_str());
+        assert_eq!(
+            event["data"][0]["token_ids"],
+            near_sdk::serde_json::json!(["0", "1"])
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "transfers must not be empty")]
+    fn batch_transfer_rejects_empty_batch() {
+        let (mut context, mut contract) = setup();
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_batch_transfer(vec![]);
+    }
+
+    #[test]
+    fn payout_two_way_split_rounds_down_to_owner() {
+        let (mut context, mut contract) = setup();
+        set_caller(&mut context, accounts(0), MINT_DEPOSIT);
+        let royalty = HashMap::from([(accounts(2), 1000), (accounts(3), 3333)]);
+        contract.nft_mint(
+            "0".to_string(),
+            accounts(1),
+            sample_token_metadata(),
+            Some(royalty),
+            None,
+            None,
+        );
+
+        let payout = contract
+            .nft_payout("0".to_string(), U128(1001), None)
+            .payout;
+
+        assert_eq!(payout.len(), 3);
+        assert_eq!(payout[&accounts(2)], U128(100));
+        assert_eq!(payout[&accounts(3)], U128(333));
+        // The owner gets what the rounded down royalties leave
+        assert_eq!(payout[&accounts(1)], U128(568));
+    }
+
+    #[test]
+    fn owned_event_data_serializes_like_borrowed() {
+        let owner_id = accounts(1);
+        let receiver_id = accounts(2);
+        let mint = || NftMintData::new(&owner_id, vec!["0", "1"], Some("memo"));
+        let transfer = || NftTransferData::new(&owner_id, &receiver_id, vec!["0"], None, None);
+
+        let owned_mint = NftMintDataOwned::from(mint());
+        let owned_transfer = NftTransferDataOwned {
+            old_owner_id: owner_id.to_string(),
+            new_owner_id: receiver_id.to_string(),
+            token_ids: vec!["0".to_string()],
+            authorized_id: None,
+            memo: None,
+        };
+
+        assert_eq!(
+            NearEvent::nft_mint(vec![(&owned_mint).into()]).to_json_string(),
+            NearEvent::nft_mint(vec![mint()]).to_json_string()
+        );
+        assert_eq!(
+            NearEvent::nft_transfer(vec![(&owned_transfer).into()]).to_json_string(),
+            NearEvent::nft_transfer(vec![transfer()]).to_json_string()
+        );
+    }
+
+    #[test]
+    fn update_metadata_persists_and_emits_event() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+
+        set_caller(&mut context, accounts(0), 1);
+        let token_metadata = TokenMetadata {
+            title: Some("Tharsis".into()),
+            ..sample_token_metadata()
+        };
+        contract.nft_update_metadata("0".to_string(), token_metadata.clone());
+
+        let token = contract.nft_token("0".to_string()).unwrap();
+        assert_eq!(token.metadata.unwrap().title, token_metadata.title);
+        let event = event::last_event();
+        assert_eq!(event["event"], "nft_metadata_update");
+        assert_eq!(
+            event["data"],
+            near_sdk::serde_json::json!([{ "token_ids": ["0"] }])
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Requires attached deposit of exactly 1 yoctoNEAR")]
+    fn update_metadata_requires_one_yocto() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+
+        set_caller(&mut context, accounts(0), 0);
+        contract.nft_update_metadata("0".to_string(), sample_token_metadata());
+    }
+
+    fn sample_contract_metadata() -> NFTContractMetadata {
+        NFTContractMetadata {
+            spec: NFT_METADATA_SPEC.to_string(),
+            name: "Mars".to_string(),
+            symbol: "MARS".to_string(),
+            icon: None,
+            base_uri: None,
+            reference: None,
+            reference_hash: None,
+        }
+    }
+
+    #[test]
+    fn update_contract_metadata_replaces_metadata() {
+        let (mut context, mut contract) = setup();
+
+        set_caller(&mut context, accounts(0), 1);
+        contract.update_contract_metadata(sample_contract_metadata());
+
+        assert_eq!(contract.nft_metadata().name, "Mars");
+        assert_eq!(event::last_event()["event"], "contract_metadata_update");
+    }
+
+    #[test]
+    #[should_panic(expected = "Unauthorized")]
+    fn update_contract_metadata_rejects_non_owner() {
+        let (mut context, mut contract) = setup();
+
+        set_caller(&mut context, accounts(1), 1);
+        contract.update_contract_metadata(sample_contract_metadata());
+    }
+
+    #[test]
+    fn minter_can_mint() {
+        let (mut context, mut contract) = setup();
+        set_caller(&mut context, accounts(0), 1);
+        contract.add_minter(accounts(1));
+
+        set_caller(&mut context, accounts(1), MINT_DEPOSIT);
+        contract.nft_mint(
+            "0".to_string(),
+            accounts(2),
+            sample_token_metadata(),
+            None,
+            None,
+            None,
+        );
+
+        assert_eq!(
+            contract.nft_token("0".to_string()).unwrap().owner_id,
+            accounts(2)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Unauthorized minter")]
+    fn non_minter_cannot_mint() {
+        let (mut context, mut contract) = setup();
+        set_caller(&mut context, accounts(1), MINT_DEPOSIT);
+        contract.nft_mint(
+            "0".to_string(),
+            accounts(1),
+            sample_token_metadata(),
+            None,
+            None,
+            None,
+        );
+    }
+
+    #[test]
+    fn transfers_resume_after_unpause() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        set_caller(&mut context, accounts(0), 1);
+        contract.pause();
+        contract.unpause();
+
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_transfer(accounts(2), "0".to_string(), None, None);
+
+        assert_eq!(
+            contract.nft_token("0".to_string()).unwrap().owner_id,
+            accounts(2)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Contract is paused")]
+    fn paused_contract_rejects_transfers() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        set_caller(&mut context, accounts(0), 1);
+        contract.pause();
+
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_transfer(accounts(2), "0".to_string(), None, None);
+    }
+
+    #[test]
+    fn pending_owner_accepts_ownership() {
+        let (mut context, mut contract) = setup();
+        set_caller(&mut context, accounts(0), 1);
+        contract.propose_owner(accounts(1));
+
+        set_caller(&mut context, accounts(1), 1);
+        contract.accept_ownership();
+
+        assert_eq!(contract.tokens.owner_id, accounts(1));
+        assert_eq!(event::last_event()["event"], "ownership_transferred");
+    }
+
+    #[test]
+    #[should_panic(expected = "Only the pending owner can accept ownership")]
+    fn non_pending_account_cannot_accept_ownership() {
+        let (mut context, mut contract) = setup();
+        set_caller(&mut context, accounts(0), 1);
+        contract.propose_owner(accounts(1));
+
+        set_caller(&mut context, accounts(2), 1);
+        contract.accept_ownership();
+    }
+
+    #[test]
+    fn tokens_for_owner_paged_returns_total_count() {
+        let (mut context, mut contract) = setup();
+        for token_id in ["0", "1", "2"] {
+            mint(&mut context, &mut contract, token_id, accounts(1));
+        }
+
+        let (tokens, supply) = contract.nft_tokens_for_owner_paged(accounts(1), None, Some(2));
+
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(supply, U128(3));
+    }
+
+    #[test]
+    fn resolve_transfer_rollback_restores_approvals() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        let approval_id = approve(&mut context, &mut contract, "0", accounts(1), accounts(3));
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_transfer_call(accounts(2), "0".to_string(), None, None, "".to_string());
+        assert!(contract
+            .nft_token("0".to_string())
+            .unwrap()
+            .approved_account_ids
+            .unwrap()
+            .is_empty());
+
+        set_promise_result(&mut context, PromiseResult::Successful(b"true".to_vec()));
+        contract.nft_resolve_transfer(
+            accounts(1),
+            accounts(2),
+            "0".to_string(),
+            Some(HashMap::from([(accounts(3), approval_id)])),
+        );
+
+        let token = contract.nft_token("0".to_string()).unwrap();
+        assert_eq!(token.owner_id, accounts(1));
+        assert_eq!(
+            token.approved_account_ids.unwrap(),
+            HashMap::from([(accounts(3), approval_id)])
+        );
+    }
+
+    #[test]
+    fn resolve_transfer_of_burned_token_keeps_transfer() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_transfer_call(accounts(2), "0".to_string(), None, None, "".to_string());
+        set_caller(&mut context, accounts(2), 1);
+        contract.nft_burn(vec!["0".to_string()], None, None);
+
+        set_promise_result(&mut context, PromiseResult::Successful(b"true".to_vec()));
+        let kept = contract.nft_resolve_transfer(
+            accounts(1),
+            accounts(2),
+            "0".to_string(),
+            Some(HashMap::new()),
+        );
+
+        assert!(kept);
+        assert!(event::logged_events().is_empty());
+    }
+
+    #[test]
+    fn burn_clears_approvals_and_refunds_storage() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        approve(&mut context, &mut contract, "0", accounts(1), accounts(2));
+
+        set_caller(&mut context, accounts(1), 1);
+        let initial_storage_usage = env::storage_usage();
+        contract.nft_burn(vec!["0".to_string()], None, None);
+        let storage_released = initial_storage_usage - env::storage_usage();
+
+        assert!(contract.nft_token("0".to_string()).is_none());
+        let approvals_by_id = contract.tokens.approvals_by_id.as_ref().unwrap();
+        assert!(approvals_by_id.get(&"0".to_string()).is_none());
+        assert!(contract.royalties.get(&"0".to_string()).is_none());
+        assert_eq!(
+            transferred_to(&accounts(1)),
+            env::storage_byte_cost() * Balance::from(storage_released)
+        );
+    }
+
+    #[test]
+    fn mint_logs_storage_refund() {
+        let (mut context, mut contract) = setup();
+        set_caller(&mut context, accounts(0), MINT_DEPOSIT);
+        let initial_storage_usage = env::storage_usage();
+        contract.nft_mint(
+            "0".to_string(),
+            accounts(1),
+            sample_token_metadata(),
+            None,
+            None,
+            None,
+        );
+        let storage_cost =
+            env::storage_byte_cost() * Balance::from(env::storage_usage() - initial_storage_usage);
+
+        let refund = event::logged_events()
+            .into_iter()
+            .find(|event| event["event"] == "storage_refund")
+            .unwrap();
+        assert_eq!(refund["data"][0]["account_id"], accounts(0).as_str());
+        assert_eq!(
+            refund["data"][0]["amount"],
+            (MINT_DEPOSIT - storage_cost).to_string()
+        );
+    }
+
+    #[test]
+    fn simulate_mint_event_matches_mint_log() {
+        let (mut context, mut contract) = setup();
+        let simulated = contract.simulate_mint_event(vec!["0".to_string()], accounts(1), None);
+
+        let json = simulated.strip_prefix("EVENT_JSON:").unwrap();
+        let event: near_sdk::serde_json::Value = near_sdk::serde_json::from_str(json).unwrap();
+        assert_eq!(event["event"], "nft_mint");
+        assert_eq!(event["data"][0]["owner_id"], accounts(1).as_str());
+        mint(&mut context, &mut contract, "0", accounts(1));
+        assert!(event::logged_events().contains(&event));
+    }
+
+    #[test]
+    fn burning_three_tokens_emits_one_event() {
+        let (mut context, mut contract) = setup();
+        for token_id in ["0", "1", "2"] {
+            mint(&mut context, &mut contract, token_id, accounts(1));
+        }
+
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_burn(
+            vec!["0".to_string(), "1".to_string(), "2".to_string()],
+            None,
+            None,
+        );
+
+        let events: Vec<_> = event::logged_events()
+            .into_iter()
+            .filter(|event| event["event"] == "nft_burn")
+            .collect();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["data"].as_array().unwrap().len(), 1);
+        assert_eq!(
+            events[0]["data"][0]["token_ids"],
+            near_sdk::serde_json::json!(["0", "1", "2"])
+        );
+    }
+
+    #[test]
+    fn operator_transfer_sets_authorized_id() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        let approval_id = approve(&mut context, &mut contract, "0", accounts(1), accounts(2));
+
+        set_caller(&mut context, accounts(2), 1);
+        contract.nft_transfer(accounts(3), "0".to_string(), Some(approval_id), None);
+
+        let event = event::last_event();
+        assert_eq!(event["data"][0]["authorized_id"], accounts(2).as_str());
+        assert_eq!(event["data"][0]["old_owner_id"], accounts(1).as_str());
+    }
+
+    #[test]
+    fn owner_transfer_omits_authorized_id() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_transfer(accounts(3), "0".to_string(), None, None);
+
+        assert!(event::last_event()["data"][0]
+            .get("authorized_id")
+            .is_none());
+    }
+
+    #[test]
+    fn generic_event_json_shape() {
+        let event = GenericEvent::new(
+            "nep245",
+            "1.0.0",
+            "mt_mint",
+            json!([{ "owner_id": "bob", "token_ids": ["0"], "amounts": ["10"] }]),
+        );
+
+        assert_eq!(
+            event.to_json_event_string(),
+            r#"EVENT_JSON:{"standard":"nep245","version":"1.0.0","event":"mt_mint","data":[{"amounts":["10"],"owner_id":"bob","token_ids":["0"]}]}"#
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Token id must not be empty")]
+    fn mint_rejects_empty_token_id() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "", accounts(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "Token id must be at most 256 bytes")]
+    fn mint_rejects_oversized_token_id() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, &"a".repeat(257), accounts(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "Token id must not contain control characters")]
+    fn mint_rejects_control_characters_in_token_id() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "a\nb", accounts(1));
+    }
+
+    #[test]
+    fn mint_accepts_longest_token_id() {
+        let (mut context, mut contract) = setup();
+        let token_id = "a".repeat(256);
+        mint(&mut context, &mut contract, &token_id, accounts(1));
+
+        assert!(contract.nft_token(token_id).is_some());
+    }
+
+    #[test]
+    fn minting_existing_token_id_panics_and_keeps_owner() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            mint(&mut context, &mut contract, "0", accounts(2))
+        }));
+
+        let error = result.unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<String>().unwrap(),
+            "Token already exists"
+        );
+        assert_eq!(
+            contract.nft_token("0".to_string()).unwrap().owner_id,
+            accounts(1)
+        );
+    }
+
+    #[test]
+    fn tokens_batch_keeps_order_and_missing_ids() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        mint(&mut context, &mut contract, "2", accounts(2));
+
+        let tokens =
+            contract.nft_tokens_batch(vec!["2".to_string(), "1".to_string(), "0".to_string()]);
+
+        let token_ids: Vec<_> = tokens
+            .iter()
+            .map(|token| token.as_ref().map(|token| token.token_id.as_str()))
+            .collect();
+        assert_eq!(token_ids, vec![Some("2"), None, Some("0")]);
+    }
+
+    fn mint_soulbound(context: &mut VMContextBuilder, contract: &mut Contract, token_id: &str) {
+        set_caller(context, accounts(0), MINT_DEPOSIT);
+        contract.nft_mint(
+            token_id.to_string(),
+            accounts(1),
+            sample_token_metadata(),
+            None,
+            Some(true),
+            None,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Token is soulbound")]
+    fn soulbound_token_rejects_transfer() {
+        let (mut context, mut contract) = setup();
+        mint_soulbound(&mut context, &mut contract, "0");
+
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_transfer(accounts(2), "0".to_string(), None, None);
+    }
+
+    #[test]
+    fn soulbound_token_can_be_burned_by_owner() {
+        let (mut context, mut contract) = setup();
+        mint_soulbound(&mut context, &mut contract, "0");
+
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_burn(vec!["0".to_string()], None, None);
+
+        assert!(contract.nft_token("0".to_string()).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "Token is locked")]
+    fn locked_token_rejects_owner_transfer() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        approve(&mut context, &mut contract, "0", accounts(1), accounts(2));
+        set_caller(&mut context, accounts(2), MINT_DEPOSIT);
+        contract.lock_token("0".to_string());
+
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_transfer(accounts(3), "0".to_string(), None, None);
+    }
+
+    #[test]
+    fn lock_holder_can_transfer_locked_token() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        let approval_id = approve(&mut context, &mut contract, "0", accounts(1), accounts(2));
+        set_caller(&mut context, accounts(2), MINT_DEPOSIT);
+        contract.lock_token("0".to_string());
+
+        set_caller(&mut context, accounts(2), 1);
+        contract.nft_transfer(accounts(3), "0".to_string(), Some(approval_id), None);
+
+        assert_eq!(
+            contract.nft_token("0".to_string()).unwrap().owner_id,
+            accounts(3)
+        );
+        assert!(contract.nft_locked_by("0".to_string()).is_none());
+    }
+
+    /// Approves `accounts(2)` for `token_id` of `accounts(1)` until `expires_at_ns`.
+    fn approve_with_expiry(
+        context: &mut VMContextBuilder,
+        contract: &mut Contract,
+        token_id: &str,
+        expires_at_ns: u64,
+    ) -> u64 {
+        set_caller(context, accounts(1), MINT_DEPOSIT);
+        contract.approve_with_expiry(token_id.to_string(), accounts(2), None, U64(expires_at_ns));
+        contract
+            .current_approval_id(&token_id.to_string(), &accounts(2))
+            .unwrap()
+    }
+
+    #[test]
+    fn approval_works_before_expiry() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        let approval_id = approve_with_expiry(&mut context, &mut contract, "0", 1_000);
+
+        context.block_timestamp(999);
+        set_caller(&mut context, accounts(2), 1);
+        contract.nft_transfer(accounts(3), "0".to_string(), Some(approval_id), None);
+
+        assert_eq!(
+            contract.nft_token("0".to_string()).unwrap().owner_id,
+            accounts(3)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Approval has expired")]
+    fn approval_fails_after_expiry() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        let approval_id = approve_with_expiry(&mut context, &mut contract, "0", 1_000);
+
+        context.block_timestamp(1_000);
+        set_caller(&mut context, accounts(2), 1);
+        contract.nft_transfer(accounts(3), "0".to_string(), Some(approval_id), None);
+    }
+
+    #[test]
+    fn approve_with_expiry_charges_expiry_storage() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+
+        set_caller(&mut context, accounts(1), MINT_DEPOSIT);
+        let initial_storage_usage = env::storage_usage();
+        contract.approve_with_expiry("0".to_string(), accounts(2), None, U64(1_000));
+        let storage_cost =
+            env::storage_byte_cost() * Balance::from(env::storage_usage() - initial_storage_usage);
+
+        assert_eq!(transferred_to(&accounts(1)), MINT_DEPOSIT - storage_cost);
+    }
+
+    #[test]
+    fn transfer_and_revoke_remove_expiries() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        mint(&mut context, &mut contract, "1", accounts(1));
+        mint(&mut context, &mut contract, "2", accounts(1));
+        let expiry_key = |token_id: &str| (token_id.to_string(), accounts(2));
+
+        approve_with_expiry(&mut context, &mut contract, "0", 1_000);
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_transfer(accounts(3), "0".to_string(), None, None);
+        assert!(contract.approval_expiries.get(&expiry_key("0")).is_none());
+
+        approve_with_expiry(&mut context, &mut contract, "1", 1_000);
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_revoke("1".to_string(), accounts(2));
+        assert!(contract.approval_expiries.get(&expiry_key("1")).is_none());
+
+        approve_with_expiry(&mut context, &mut contract, "2", 1_000);
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_revoke_all("2".to_string());
+        assert!(contract.approval_expiries.get(&expiry_key("2")).is_none());
+    }
+
+    fn mint_with_reference_hash(
+        context: &mut VMContextBuilder,
+        contract: &mut Contract,
+        reference_hash: Option<Vec<u8>>,
+    ) {
+        set_caller(context, accounts(0), MINT_DEPOSIT);
+        let token_metadata = TokenMetadata {
+            reference: Some("https://example.com/0.json".to_string()),
+            reference_hash: reference_hash.map(Base64VecU8),
+            ..sample_token_metadata()
+        };
+        contract.nft_mint(
+            "0".to_string(),
+            accounts(1),
+            token_metadata,
+            None,
+            None,
+            None,
+        );
+    }
+
+    #[test]
+    fn mint_accepts_sha256_reference_hash() {
+        let (mut context, mut contract) = setup();
+        mint_with_reference_hash(&mut context, &mut contract, Some(vec![7; 32]));
+
+        assert!(contract.nft_token("0".to_string()).is_some());
+    }
+
+    #[test]
+    fn mint_accepts_absent_reference_hash() {
+        let (mut context, mut contract) = setup();
+        mint_with_reference_hash(&mut context, &mut contract, None);
+
+        assert!(contract.nft_token("0".to_string()).is_some());
+    }
+
+    #[test]
+    #[should_panic(expected = "reference_hash must be a 32-byte SHA-256 hash")]
+    fn mint_rejects_wrong_length_reference_hash() {
+        let (mut context, mut contract) = setup();
+        mint_with_reference_hash(&mut context, &mut contract, Some(vec![7; 31]));
+    }
+
+    #[test]
+    fn migrate_keeps_tokens_and_defaults_new_fields() {
+        #[derive(BorshSerialize)]
+        struct OldContract {
+            tokens: NonFungibleToken,
+            metadata: LazyOption<NFTContractMetadata>,
+        }
+
+        let mut context = get_context(accounts(0));
+        testing_env!(context.attached_deposit(MINT_DEPOSIT).build());
+        let mut tokens = NonFungibleToken::new(
+            StorageKey::NonFungibleToken,
+            accounts(0),
+            Some(StorageKey::TokenMetadata),
+            Some(StorageKey::Enumeration),
+            Some(StorageKey::Approval),
+        );
+        tokens.internal_mint("0".to_string(), accounts(1), Some(sample_token_metadata()));
+        tokens.internal_mint("1".to_string(), accounts(1), Some(sample_token_metadata()));
+        let metadata = LazyOption::new(StorageKey::Metadata, Some(&sample_contract_metadata()));
+        env::state_write(&OldContract { tokens, metadata });
+
+        set_caller(&mut context, contract_id(), 0);
+        let mut contract = Contract::migrate();
+
+        assert_eq!(contract.nft_metadata().name, "Mars");
+        assert_eq!(
+            contract.nft_token("0".to_string()).unwrap().owner_id,
+            accounts(1)
+        );
+        assert!(!contract.is_paused());
+        assert!(contract.minters.is_empty());
+        assert!(contract.pending_owner.is_none());
+        // Balances of existing owners are counted from the enumeration
+        assert_eq!(contract.nft_balance(accounts(1)), U128(2));
+
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_transfer(accounts(2), "0".to_string(), None, None);
+        assert_eq!(
+            contract.nft_token("0".to_string()).unwrap().owner_id,
+            accounts(2)
+        );
+        assert_eq!(contract.nft_balance(accounts(1)), U128(1));
+        assert_eq!(contract.nft_balance(accounts(2)), U128(1));
+    }
+
+    /// Checks that the cached balance of `account_id` matches its enumerated tokens.
+    fn assert_balance_matches_enumeration(contract: &Contract, account_id: AccountId) {
+        assert_eq!(
+            contract.nft_balance(account_id.clone()),
+            contract.nft_supply_for_owner(account_id)
+        );
+    }
+
+    #[test]
+    fn balance_matches_enumeration_after_mint_transfer_and_burn() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        mint(&mut context, &mut contract, "1", accounts(1));
+        assert_eq!(contract.nft_balance(accounts(1)), U128(2));
+        assert_balance_matches_enumeration(&contract, accounts(1));
+
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_transfer(accounts(2), "0".to_string(), None, None);
+        assert_eq!(contract.nft_balance(accounts(1)), U128(1));
+        assert_eq!(contract.nft_balance(accounts(2)), U128(1));
+        assert_balance_matches_enumeration(&contract, accounts(1));
+        assert_balance_matches_enumeration(&contract, accounts(2));
+
+        set_caller(&mut context, accounts(2), 1);
+        contract.nft_burn(vec!["0".to_string()], None, None);
+        assert_eq!(contract.nft_balance(accounts(2)), U128(0));
+        assert_balance_matches_enumeration(&contract, accounts(2));
+    }
+
+    #[test]
+    fn events_use_version_selected_at_init() {
+        let mut context = get_context(accounts(0));
+        testing_env!(context.build());
+        let mut contract = Contract::new(
+            accounts(0),
+            sample_contract_metadata(),
+            Some(Nep171Version::V1_1_0),
+            None,
+            None,
+            None,
+            None,
+        );
+
+        mint(&mut context, &mut contract, "0", accounts(1));
+        assert_eq!(event::last_event()["version"], "1.1.0");
+
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_transfer(accounts(2), "0".to_string(), None, None);
+        assert_eq!(event::last_event()["version"], "1.1.0");
+    }
+
+    #[test]
+    fn parse_log_round_trips_mint_transfer_and_burn() {
+        let owner_id = accounts(1);
+        let receiver_id = accounts(2);
+        let events = vec![
+            NearEvent::nft_mint(vec![NftMintData::new(&owner_id, vec!["0"], Some("memo"))]),
+            NearEvent::nft_transfer(vec![NftTransferData::new(
+                &owner_id,
+                &receiver_id,
+                vec!["0"],
+                Some(&receiver_id),
+                None,
+            )]),
+            NearEvent::nft_burn(vec![NftBurnData::new(&receiver_id, vec!["0"], None, None)]),
+        ];
+
+        for event in events {
+            let parsed = NearEvent::parse_log(&event.to_json_event_string()).unwrap();
+            assert_eq!(parsed.standard, "nep171");
+            assert_eq!(
+                near_sdk::serde_json::to_string(&parsed).unwrap(),
+                event.to_json_string()
+            );
+        }
+    }
+
+    /// Returns the gas attached to the `function_name` calls created by the last call.
+    fn function_call_gas(function_name: &str) -> Vec<Gas> {
+        near_sdk::test_utils::get_created_receipts()
+            .into_iter()
+            .flat_map(|receipt| receipt.actions)
+            .filter_map(|action| match action {
+                near_sdk::mock::VmAction::FunctionCall {
+                    function_name: name,
+                    gas,
+                    ..
+                } if name == function_name => Some(gas),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn transfer_call_attaches_custom_receiver_gas() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        set_caller(&mut context, accounts(0), 1);
+        contract.set_gas_for_on_transfer(Some(Gas(50_000_000_000_000)));
+
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_transfer_call(accounts(2), "0".to_string(), None, None, "".to_string());
+
+        assert_eq!(
+            function_call_gas("nft_on_transfer"),
+            vec![Gas(50_000_000_000_000)]
+        );
+        assert_eq!(
+            function_call_gas("nft_resolve_transfer"),
+            vec![GAS_FOR_RESOLVE_TRANSFER]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "More gas is required")]
+    fn transfer_call_rejects_gas_below_custom_split() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        set_caller(&mut context, accounts(0), 1);
+        contract.set_gas_for_on_transfer(Some(Gas(50_000_000_000_000)));
+
+        context.prepaid_gas(GAS_FOR_NFT_TRANSFER_CALL + Gas(49_000_000_000_000));
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_transfer_call(accounts(2), "0".to_string(), None, None, "".to_string());
+    }
+
+    #[test]
+    fn approval_id_matches_approval() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        approve(&mut context, &mut contract, "0", accounts(1), accounts(2));
+        approve(&mut context, &mut contract, "0", accounts(1), accounts(3));
+
+        assert_eq!(
+            contract.nft_approval_id("0".to_string(), accounts(2)),
+            Some(1)
+        );
+        assert_eq!(
+            contract.nft_approval_id("0".to_string(), accounts(3)),
+            Some(2)
+        );
+        assert_eq!(contract.nft_approval_id("0".to_string(), accounts(4)), None);
+        assert_eq!(contract.nft_approval_id("1".to_string(), accounts(2)), None);
+    }
+
+    #[test]
+    fn batch_mint_to_groups_entries_by_receiver() {
+        let (mut context, mut contract) = setup();
+        set_caller(&mut context, accounts(0), MINT_DEPOSIT);
+        contract.nft_batch_mint_to(vec![
+            ("0".to_string(), accounts(1), sample_token_metadata()),
+            ("1".to_string(), accounts(2), sample_token_metadata()),
+            ("2".to_string(), accounts(1), sample_token_metadata()),
+        ]);
+
+        let events: Vec<_> = event::logged_events()
+            .into_iter()
+            .filter(|event| event["event"] == "nft_mint")
+            .collect();
+        assert_eq!(events.len(), 1);
+        assert_eq!(
+            events[0]["data"],
+            near_sdk::serde_json::json!([
+                { "owner_id": accounts(1), "token_ids": ["0", "2"] },
+                { "owner_id": accounts(2), "token_ids": ["1"] },
+            ])
+        );
+    }
+
+    #[test]
+    fn burning_does_not_decrease_total_minted() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        mint(&mut context, &mut contract, "1", accounts(1));
+
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_burn(vec!["0".to_string()], None, None);
+
+        assert_eq!(contract.nft_total_supply(), U128(1));
+        assert_eq!(contract.nft_total_minted(), U128(2));
+        assert_eq!(contract.next_token_id(), "2");
+    }
+
+    #[test]
+    fn mint_auto_generates_sequential_ids() {
+        let (mut context, mut contract) = setup();
+
+        let token_ids: Vec<_> = (0..3)
+            .map(|_| {
+                set_caller(&mut context, accounts(0), MINT_DEPOSIT);
+                contract
+                    .nft_mint_auto(accounts(1), sample_token_metadata())
+                    .token_id
+            })
+            .collect();
+
+        assert_eq!(token_ids, vec!["0", "1", "2"]);
+        assert_eq!(event::last_event()["data"][0]["token_ids"][0], "2");
+    }
+
+    #[test]
+    fn mint_auto_never_reuses_burned_ids() {
+        let (mut context, mut contract) = setup();
+        set_caller(&mut context, accounts(0), MINT_DEPOSIT);
+        contract.nft_mint_auto(accounts(1), sample_token_metadata());
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_burn(vec!["0".to_string()], None, None);
+
+        set_caller(&mut context, accounts(0), MINT_DEPOSIT);
+        let token = contract.nft_mint_auto(accounts(1), sample_token_metadata());
+
+        assert_eq!(token.token_id, "1");
+    }
+
+    #[test]
+    fn mint_editions_numbers_copies() {
+        let (mut context, mut contract) = setup();
+        set_caller(&mut context, accounts(0), MINT_DEPOSIT);
+        let tokens = contract.nft_mint_editions(
+            "poster".to_string(),
+            accounts(1),
+            sample_token_metadata(),
+            3,
+        );
+
+        let token_ids: Vec<_> = tokens.iter().map(|token| token.token_id.as_str()).collect();
+        assert_eq!(token_ids, vec!["poster:1", "poster:2", "poster:3"]);
+        for token in &tokens {
+            assert_eq!(token.metadata.as_ref().unwrap().copies, Some(3));
+        }
+        let event = event::last_event();
+        assert_eq!(event["event"], "nft_mint");
+        assert_eq!(
+            event["data"][0]["token_ids"],
+            near_sdk::serde_json::json!(["poster:1", "poster:2", "poster:3"])
+        );
+    }
+
+    #[test]
+    fn event_standards_follow_configured_version() {
+        let (mut context, mut contract) = setup();
+
+        let standards = contract.event_standards();
+
+        let pair = |standard: &str, version: &str| (standard.to_string(), version.to_string());
+        for expected in [
+            pair("nep171", "1.0.0"),
+            pair("nep171", "1.2.0"),
+            pair("nep178", "1.0.0"),
+            pair("storage", "1.0.0"),
+            pair("nft_lock", "1.0.0"),
+            pair("contract_ownership", "1.0.0"),
+            pair("nft_operator", "1.0.0"),
+            pair("nft_royalty", "1.0.0"),
+        ] {
+            assert!(standards.contains(&expected), "missing {:?}", expected);
+        }
+        // No supply milestones were configured
+        assert!(!standards.contains(&pair("nft_supply", "1.0.0")));
+
+        set_caller(&mut context, accounts(0), 1);
+        contract.freeze_royalties();
+        assert!(!contract
+            .event_standards()
+            .contains(&pair("nft_royalty", "1.0.0")));
+
+        set_caller(&mut context, accounts(0), 1);
+        contract.set_emit_events(false);
+        assert!(contract.event_standards().is_empty());
+
+        let context = get_context(accounts(0));
+        testing_env!(context.build());
+        let contract = Contract::new(
+            accounts(0),
+            sample_contract_metadata(),
+            Some(Nep171Version::V1_2_0),
+            None,
+            None,
+            None,
+            Some(vec![10]),
+        );
+        let standards = contract.event_standards();
+        assert!(!standards.contains(&pair("nep171", "1.0.0")));
+        assert!(standards.contains(&pair("nft_supply", "1.0.0")));
+    }
+
+    #[test]
+    fn mint_memo_is_logged_only_when_present() {
+        let (mut context, mut contract) = setup();
+        set_caller(&mut context, accounts(0), MINT_DEPOSIT);
+        contract.nft_mint(
+            "0".to_string(),
+            accounts(1),
+            sample_token_metadata(),
+            None,
+            None,
+            Some("drop-1".to_string()),
+        );
+        assert_eq!(event::last_event()["data"][0]["memo"], "drop-1");
+
+        mint(&mut context, &mut contract, "1", accounts(1));
+        assert!(event::last_event()["data"][0].get("memo").is_none());
+    }
+
+    #[test]
+    fn payout_five_percent_royalty_on_100_near_sale() {
+        const NEAR: u128 = 1_000_000_000_000_000_000_000_000;
+        let (mut context, mut contract) = setup();
+        set_caller(&mut context, accounts(0), MINT_DEPOSIT);
+        contract.nft_mint(
+            "0".to_string(),
+            accounts(1),
+            sample_token_metadata(),
+            Some(HashMap::from([(accounts(2), 500)])),
+            None,
+            None,
+        );
+
+        let payout = contract
+            .nft_payout("0".to_string(), U128(100 * NEAR), Some(2))
+            .payout;
+        assert_eq!(payout[&accounts(2)], U128(5 * NEAR));
+        assert_eq!(payout[&accounts(1)], U128(95 * NEAR));
+
+        // The yoctoNEAR the royalty rounds away go to the owner
+        let payout = contract
+            .nft_payout("0".to_string(), U128(100 * NEAR + 19), Some(2))
+            .payout;
+        assert_eq!(payout[&accounts(2)], U128(5 * NEAR));
+        assert_eq!(payout[&accounts(1)], U128(95 * NEAR + 19));
+    }
+
+    #[test]
+    fn event_buffer_merges_consecutive_events_of_a_kind() {
+        let owner_id = accounts(1);
+        let mut buffer = EventBuffer::new(Nep171Version::V1_0_0);
+        buffer.push(Nep171EventKind::NftMint(vec![NftMintData::new(
+            &owner_id,
+            vec!["0"],
+            None,
+        )]));
+        buffer.push(Nep171EventKind::NftMint(vec![NftMintData::new(
+            &owner_id,
+            vec!["1"],
+            None,
+        )]));
+        buffer.push(Nep171EventKind::NftBurn(vec![NftBurnData::new(
+            &owner_id,
+            vec!["0"],
+            None,
+            None,
+        )]));
+
+        let mut logs = Vec::new();
+        buffer.flush_with(|event| logs.push(event.to_json_event_string()));
+
+        assert!(buffer.is_empty());
+        assert_eq!(logs.len(), 2);
+        assert!(logs[0].contains(r#""event":"nft_mint""#));
+        assert!(logs[0].contains(
+            r#"{"owner_id":"bob","token_ids":["0"]},{"owner_id":"bob","token_ids":["1"]}"#
+        ));
+        assert!(logs[1].contains(r#""event":"nft_burn""#));
+    }
+
+    #[test]
+    fn event_buffer_logs_nothing_unless_flushed() {
+        let (_, contract) = setup();
+        let owner_id = accounts(1);
+        let mut buffer = EventBuffer::new(contract.event_version);
+        buffer.push(Nep171EventKind::NftMint(vec![NftMintData::new(
+            &owner_id,
+            vec!["0"],
+            None,
+        )]));
+        drop(buffer);
+
+        assert!(event::logged_events().is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Contract is not locked")]
+    fn lock_guard_rejects_unlocked_contract() {
+        let (_, contract) = setup();
+        contract.assert_contract_is_locked();
+    }
+
+    #[test]
+    fn lock_guard_passes_once_deployer_locks_contract() {
+        let (mut context, mut contract) = setup();
+        set_caller(&mut context, contract_id(), 0);
+        contract.set_contract_is_locked(true);
+
+        assert!(contract.contract_is_locked());
+        contract.assert_contract_is_locked();
+    }
+
+    #[test]
+    fn transfer_ret_returns_token_of_receiver() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        approve(&mut context, &mut contract, "0", accounts(1), accounts(3));
+
+        set_caller(&mut context, accounts(1), 1);
+        let token = contract.nft_transfer_ret(accounts(2), "0".to_string(), None, None);
+
+        assert_eq!(token.owner_id, accounts(2));
+        assert!(token.approved_account_ids.unwrap().is_empty());
+    }
+
+    #[test]
+    fn revoke_event_json() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        approve(&mut context, &mut contract, "0", accounts(1), accounts(2));
+
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_revoke("0".to_string(), accounts(2));
+
+        assert_eq!(
+            event::last_event(),
+            json!({
+                "standard": "nep178",
+                "version": "1.0.0",
+                "event": "nft_revoke",
+                "data": [
+                    {
+                        "owner_id": "bob",
+                        "token_id": "0",
+                        "account_id": "charlie"
+                    }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn revoke_all_event_json() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        approve(&mut context, &mut contract, "0", accounts(1), accounts(2));
+
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_revoke_all("0".to_string());
+
+        assert_eq!(
+            event::last_event(),
+            json!({
+                "standard": "nep178",
+                "version": "1.0.0",
+                "event": "nft_revoke_all",
+                "data": [
+                    {
+                        "owner_id": "bob",
+                        "token_id": "0"
+                    }
+                ]
+            })
+        );
+    }
+
+    /// Deploys the contract owned by `accounts(0)` with a supply capped at `max_supply`.
+    fn setup_with_max_supply(max_supply: u64) -> (VMContextBuilder, Contract) {
+        let context = get_context(accounts(0));
+        testing_env!(context.build());
+        let contract = Contract::new(
+            accounts(0),
+            sample_contract_metadata(),
+            None,
+            Some(max_supply),
+            None,
+            None,
+            None,
+        );
+        (context, contract)
+    }
+
+    #[test]
+    fn mint_up_to_max_supply() {
+        let (mut context, mut contract) = setup_with_max_supply(2);
+        mint(&mut context, &mut contract, "0", accounts(1));
+        mint(&mut context, &mut contract, "1", accounts(1));
+
+        assert_eq!(contract.nft_total_supply(), U128(2));
+    }
+
+    #[test]
+    #[should_panic(expected = "Max supply reached")]
+    fn mint_beyond_max_supply_panics_even_after_burn() {
+        let (mut context, mut contract) = setup_with_max_supply(2);
+        mint(&mut context, &mut contract, "0", accounts(1));
+        mint(&mut context, &mut contract, "1", accounts(1));
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_burn(vec!["0".to_string()], None, None);
+
+        mint(&mut context, &mut contract, "2", accounts(1));
+    }
+
+    #[test]
+    fn metadata_hash_changes_after_update() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        let hash = contract.nft_token_metadata_hash("0".to_string()).unwrap();
+        assert_eq!(hash.0.len(), 32);
+
+        set_caller(&mut context, accounts(0), 1);
+        let token_metadata = TokenMetadata {
+            title: Some("Tharsis".into()),
+            ..sample_token_metadata()
+        };
+        contract.nft_update_metadata("0".to_string(), token_metadata);
+
+        assert_ne!(
+            contract.nft_token_metadata_hash("0".to_string()).unwrap(),
+            hash
+        );
+        assert!(contract.nft_token_metadata_hash("1".to_string()).is_none());
+    }
+
+    /// Mints a 64-byte token id to a new 64-byte account and returns the estimate of the mint
+    /// deposit along with the cost of the storage it actually used.
+    fn mint_storage_cost_and_usage(
+        context: &mut VMContextBuilder,
+        contract: &mut Contract,
+    ) -> (Balance, Balance) {
+        set_caller(context, accounts(0), MINT_DEPOSIT);
+        let estimate = contract.nft_mint_storage_cost(sample_token_metadata()).0;
+        let initial_storage_usage = env::storage_usage();
+        contract.nft_mint(
+            "t".repeat(64),
+            "a".repeat(64).parse().unwrap(),
+            sample_token_metadata(),
+            None,
+            None,
+            None,
+        );
+        let used = Balance::from(env::storage_usage() - initial_storage_usage);
+        (estimate, used * env::storage_byte_cost())
+    }
+
+    #[test]
+    fn mint_storage_cost_covers_largest_mint() {
+        let (mut context, mut contract) = setup();
+        let (estimate, cost) = mint_storage_cost_and_usage(&mut context, &mut contract);
+        assert!(estimate >= cost);
+        assert!(estimate - cost <= 16 * env::storage_byte_cost());
+    }
+
+    #[test]
+    fn mint_storage_cost_counts_default_royalty() {
+        let (mut context, mut contract) =
+            setup_with_default_royalty(HashMap::from([(accounts(1), 500), (accounts(2), 250)]));
+        let (estimate, cost) = mint_storage_cost_and_usage(&mut context, &mut contract);
+        assert!(estimate >= cost);
+        assert!(estimate - cost <= 16 * env::storage_byte_cost());
+    }
+
+    #[test]
+    fn approve_many_approves_three_tokens_in_one_event() {
+        let (mut context, mut contract) = setup();
+        for token_id in ["a", "b", "c"] {
+            mint(&mut context, &mut contract, token_id, accounts(1));
+        }
+
+        set_caller(&mut context, accounts(1), MINT_DEPOSIT);
+        let token_ids = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        let approval_ids = contract.nft_approve_many(token_ids.clone(), accounts(2), None);
+
+        assert_eq!(approval_ids.len(), 3);
+        for (token_id, approval_id) in token_ids.iter().zip(&approval_ids) {
+            assert!(contract.nft_is_approved(token_id.clone(), accounts(2), Some(*approval_id)));
+        }
+        let events: Vec<_> = event::logged_events()
+            .into_iter()
+            .filter(|event| event["event"] == "nft_approve")
+            .collect();
+        assert_eq!(events.len(), 1);
+        let data = events[0]["data"].as_array().unwrap();
+        let approved: Vec<&str> = data
+            .iter()
+            .map(|entry| entry["token_id"].as_str().unwrap())
+            .collect();
+        assert_eq!(approved, ["a", "b", "c"]);
+        assert!(data
+            .iter()
+            .zip(&approval_ids)
+            .all(|(entry, approval_id)| entry["approval_id"] == *approval_id));
+    }
+
+    #[test]
+    #[should_panic(expected = "Predecessor must be the token owner")]
+    fn approve_many_requires_owning_every_token() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "a", accounts(1));
+        mint(&mut context, &mut contract, "b", accounts(3));
+
+        set_caller(&mut context, accounts(1), MINT_DEPOSIT);
+        contract.nft_approve_many(vec!["a".to_string(), "b".to_string()], accounts(2), None);
+    }
+
+    #[test]
+    fn operator_approved_for_all_transfers_any_token() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "a", accounts(1));
+        mint(&mut context, &mut contract, "b", accounts(1));
+
+        set_caller(&mut context, accounts(1), MINT_DEPOSIT);
+        contract.set_approval_for_all(accounts(2), true);
+        assert_eq!(
+            event::last_event(),
+            json!({
+                "standard": "nft_operator",
+                "version": "1.0.0",
+                "event": "approval_for_all",
+                "data": [{ "owner_id": "bob", "operator_id": "charlie", "approved": true }]
+            })
+        );
+        assert!(contract.is_approved_for_all(accounts(1), accounts(2)));
+        assert!(!contract.is_approved_for_all(accounts(2), accounts(1)));
+        // An operator isn't a per-token approval
+        assert!(!contract.nft_is_approved("a".to_string(), accounts(2), None));
+
+        set_caller(&mut context, accounts(2), 1);
+        contract.nft_transfer(accounts(3), "a".to_string(), None, None);
+        contract.nft_transfer(accounts(4), "b".to_string(), None, None);
+        assert_eq!(
+            contract.nft_token("a".to_string()).unwrap().owner_id,
+            accounts(3)
+        );
+        assert_eq!(
+            contract.nft_token("b".to_string()).unwrap().owner_id,
+            accounts(4)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Unauthorized")]
+    fn operator_cannot_transfer_once_disapproved() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "a", accounts(1));
+
+        set_caller(&mut context, accounts(1), MINT_DEPOSIT);
+        contract.set_approval_for_all(accounts(2), true);
+        set_caller(&mut context, accounts(1), 1);
+        contract.set_approval_for_all(accounts(2), false);
+        assert_eq!(event::last_event()["data"][0]["approved"], false);
+        assert!(!contract.is_approved_for_all(accounts(1), accounts(2)));
+
+        set_caller(&mut context, accounts(2), 1);
+        contract.nft_transfer(accounts(3), "a".to_string(), None, None);
+    }
+
+    #[test]
+    fn last_event_returns_latest_event_log() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_transfer(accounts(2), "0".to_string(), None, None);
+        env::log_str("not an event");
+
+        let event = event::last_event();
+        assert_eq!(event["event"], "nft_transfer");
+        assert_eq!(event["data"][0]["token_ids"][0], "0");
+    }
+
+    #[test]
+    fn metadata_updates_work_before_freeze() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+
+        set_caller(&mut context, accounts(0), 1);
+        contract.update_contract_metadata(sample_contract_metadata());
+        let token_metadata = TokenMetadata {
+            title: Some("Tharsis".into()),
+            ..sample_token_metadata()
+        };
+        contract.nft_update_metadata("0".to_string(), token_metadata.clone());
+        contract.freeze_metadata();
+
+        assert!(contract.is_metadata_frozen());
+        assert_eq!(contract.nft_metadata().name, "Mars");
+        let token = contract.nft_token("0".to_string()).unwrap();
+        assert_eq!(token.metadata.unwrap().title, token_metadata.title);
+    }
+
+    #[test]
+    #[should_panic(expected = "Metadata is frozen")]
+    fn frozen_token_metadata_cannot_be_updated() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+
+        set_caller(&mut context, accounts(0), 1);
+        contract.freeze_metadata();
+        contract.nft_update_metadata("0".to_string(), sample_token_metadata());
+    }
+
+    #[test]
+    #[should_panic(expected = "Metadata is frozen")]
+    fn frozen_contract_metadata_cannot_be_updated() {
+        let (mut context, mut contract) = setup();
+
+        set_caller(&mut context, accounts(0), 1);
+        contract.freeze_metadata();
+        contract.update_contract_metadata(sample_contract_metadata());
+    }
+
+    #[test]
+    fn tokens_for_owner_from_pages_stably_by_token_id() {
+        let (mut context, mut contract) = setup();
+        for token_id in ["e", "a", "c", "g"] {
+            mint(&mut context, &mut contract, token_id, accounts(1));
+        }
+        let token_ids = |tokens: Vec<Token>| {
+            tokens
+                .into_iter()
+                .map(|token| token.token_id)
+                .collect::<Vec<_>>()
+        };
+
+        let page = contract.nft_tokens_for_owner_from(accounts(1), None, Some(2));
+        assert_eq!(token_ids(page), ["a", "c"]);
+
+        // Tokens minted or sent away before the cursor don't shift the next page
+        mint(&mut context, &mut contract, "b", accounts(1));
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_transfer(accounts(2), "a".to_string(), None, None);
+
+        let page = contract.nft_tokens_for_owner_from(accounts(1), Some("c".to_string()), Some(2));
+        assert_eq!(token_ids(page), ["e", "g"]);
+        let page = contract.nft_tokens_for_owner_from(accounts(1), Some("g".to_string()), Some(2));
+        assert!(page.is_empty());
+        assert!(contract
+            .nft_tokens_for_owner_from(accounts(3), None, None)
+            .is_empty());
+    }
+
+    #[test]
+    fn lock_and_unlock_events_json() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        approve(&mut context, &mut contract, "0", accounts(1), accounts(2));
+
+        set_caller(&mut context, accounts(2), MINT_DEPOSIT);
+        contract.lock_token("0".to_string());
+        assert_eq!(
+            event::last_event(),
+            json!({
+                "standard": "nft_lock",
+                "version": "1.0.0",
+                "event": "nft_lock",
+                "data": [
+                    {
+                        "locker": "charlie",
+                        "token_id": "0"
+                    }
+                ]
+            })
+        );
+
+        set_caller(&mut context, accounts(2), 1);
+        contract.unlock_token("0".to_string());
+        assert_eq!(
+            event::last_event(),
+            json!({
+                "standard": "nft_lock",
+                "version": "1.0.0",
+                "event": "nft_unlock",
+                "data": [
+                    {
+                        "locker": "charlie",
+                        "token_id": "0"
+                    }
+                ]
+            })
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "testing")]
+    fn token_checked_returns_found_token() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+
+        let token = contract.nft_token_checked("0".to_string()).unwrap();
+        assert_eq!(token.owner_id, accounts(1));
+    }
+
+    #[test]
+    #[cfg(feature = "testing")]
+    fn token_checked_names_missing_token() {
+        let (_, contract) = setup();
+
+        assert_eq!(
+            contract.nft_token_checked("0".to_string()).unwrap_err(),
+            "Token 0 not found"
+        );
+    }
+
+    #[test]
+    fn token_extra_json_reads_nested_field() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "plain", accounts(1));
+        for (token_id, extra) in [
+            ("json", r#"{"attributes":{"elevation":21900}}"#),
+            ("text", "not json"),
+        ] {
+            set_caller(&mut context, accounts(0), MINT_DEPOSIT);
+            let token_metadata = TokenMetadata {
+                extra: Some(extra.to_string()),
+                ..sample_token_metadata()
+            };
+            contract.nft_mint(
+                token_id.to_string(),
+                accounts(1),
+                token_metadata,
+                None,
+                None,
+                None,
+            );
+        }
+
+        let extra = contract.nft_token_extra_json("json".to_string()).unwrap();
+        assert_eq!(extra["attributes"]["elevation"], 21900);
+        assert!(contract.nft_token_extra_json("text".to_string()).is_none());
+        assert!(contract.nft_token_extra_json("plain".to_string()).is_none());
+        assert!(contract
+            .nft_token_extra_json("missing".to_string())
+            .is_none());
+    }
+
+    #[test]
+    fn mint_and_approve_returns_valid_approval_id() {
+        let (mut context, mut contract) = setup();
+        set_caller(&mut context, accounts(0), MINT_DEPOSIT);
+        let (token, approval_id) = contract.nft_mint_and_approve(
+            "0".to_string(),
+            accounts(1),
+            sample_token_metadata(),
+            accounts(2),
+        );
+
+        assert_eq!(token.owner_id, accounts(1));
+        assert!(contract.nft_is_approved("0".to_string(), accounts(2), Some(approval_id)));
+        let events: Vec<_> = event::logged_events()
+            .into_iter()
+            .map(|event| event["event"].as_str().unwrap().to_string())
+            .filter(|event| event != "storage_refund")
+            .collect();
+        assert_eq!(events, ["nft_mint", "nft_approve"]);
+    }
+
+    #[test]
+    fn mint_and_approve_charges_storage_of_both() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        mint(&mut context, &mut contract, "1", accounts(1));
+        let mint_refund = transferred_to(&accounts(0));
+
+        set_caller(&mut context, accounts(0), MINT_DEPOSIT);
+        contract.nft_mint_and_approve(
+            "2".to_string(),
+            accounts(1),
+            sample_token_metadata(),
+            accounts(2),
+        );
+        assert!(transferred_to(&accounts(0)) < mint_refund);
+    }
+
+    /// Deploys the contract owned by `accounts(0)` with `metadata`.
+    fn setup_with_metadata(metadata: NFTContractMetadata) -> Contract {
+        testing_env!(get_context(accounts(0)).build());
+        Contract::new(accounts(0), metadata, None, None, None, None, None)
+    }
+
+    #[test]
+    fn new_accepts_ipfs_base_uri() {
+        let contract = setup_with_metadata(NFTContractMetadata {
+            base_uri: Some(
+                "ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi".into(),
+            ),
+            ..sample_contract_metadata()
+        });
+        assert!(contract
+            .nft_metadata()
+            .base_uri
+            .unwrap()
+            .starts_with("ipfs://"));
+    }
+
+    #[test]
+    #[should_panic(expected = "base_uri must be an https:// or ipfs:// URL")]
+    fn new_rejects_http_base_uri() {
+        setup_with_metadata(NFTContractMetadata {
+            base_uri: Some("http://example.com/".into()),
+            ..sample_contract_metadata()
+        });
+    }
+
+    #[test]
+    #[should_panic(expected = "Data URI icon must be at most 65536 bytes")]
+    fn new_rejects_oversized_data_uri_icon() {
+        let icon = format!("data:image/svg+xml,{}", "a".repeat(MAX_DATA_URI_ICON_LEN));
+        setup_with_metadata(NFTContractMetadata {
+            icon: Some(icon),
+            ..sample_contract_metadata()
+        });
+    }
+
+    #[test]
+    fn resolved_urls_join_relative_paths_with_base_uri() {
+        let mut contract = setup_with_metadata(NFTContractMetadata {
+            base_uri: Some("https://mars.example/assets/".into()),
+            ..sample_contract_metadata()
+        });
+        let mut context = get_context(accounts(0));
+        for (token_id, media) in [
+            ("relative", "/olympus.png"),
+            (
+                "absolute",
+                "ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi",
+            ),
+        ] {
+            set_caller(&mut context, accounts(0), MINT_DEPOSIT);
+            let token_metadata = TokenMetadata {
+                media: Some(media.to_string()),
+                reference: Some("olympus.json".to_string()),
+                ..sample_token_metadata()
+            };
+            contract.nft_mint(
+                token_id.to_string(),
+                accounts(1),
+                token_metadata,
+                None,
+                None,
+                None,
+            );
+        }
+
+        let urls = contract
+            .nft_token_resolved_urls("relative".to_string())
+            .unwrap();
+        assert_eq!(
+            urls.media.unwrap(),
+            "https://mars.example/assets/olympus.png"
+        );
+        assert_eq!(
+            urls.reference.unwrap(),
+            "https://mars.example/assets/olympus.json"
+        );
+        let urls = contract
+            .nft_token_resolved_urls("absolute".to_string())
+            .unwrap();
+        assert_eq!(
+            urls.media.unwrap(),
+            "ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi"
+        );
+        assert!(contract
+            .nft_token_resolved_urls("missing".to_string())
+            .is_none());
+    }
+
+    #[test]
+    fn approved_account_burn_sets_authorized_id() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        let approval_id = approve(&mut context, &mut contract, "0", accounts(1), accounts(2));
+
+        set_caller(&mut context, accounts(2), 1);
+        contract.nft_burn(vec!["0".to_string()], None, Some(vec![approval_id]));
+
+        assert!(contract.nft_token("0".to_string()).is_none());
+        let event = event::last_event();
+        assert_eq!(event["event"], "nft_burn");
+        assert_eq!(event["data"][0]["owner_id"], accounts(1).as_str());
+        assert_eq!(event["data"][0]["authorized_id"], accounts(2).as_str());
+    }
+
+    #[test]
+    fn owner_burn_omits_authorized_id() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_burn(vec!["0".to_string()], None, None);
+
+        assert!(event::last_event()["data"][0]
+            .get("authorized_id")
+            .is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "The actual approval_id is different from the given approval_id")]
+    fn approved_account_burn_rejects_stale_approval_id() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        let approval_id = approve(&mut context, &mut contract, "0", accounts(1), accounts(2));
+
+        set_caller(&mut context, accounts(2), 1);
+        contract.nft_burn(vec!["0".to_string()], None, Some(vec![approval_id + 1]));
+    }
+
+    #[test]
+    #[should_panic(expected = "Unauthorized")]
+    fn unapproved_account_cannot_burn() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+
+        set_caller(&mut context, accounts(2), 1);
+        contract.nft_burn(vec!["0".to_string()], None, None);
+    }
+
+    #[test]
+    fn balances_match_enumeration_after_random_operations() {
+        let (mut context, mut contract) = setup();
+        let owners: Vec<AccountId> = (1..5).map(accounts).collect();
+        let mut tokens: Vec<(String, usize)> = Vec::new();
+        let mut next_token_id = 0;
+        // Deterministic linear congruential generator, so a failure can be replayed
+        let mut seed: u64 = 0x2545_f491_4f6c_dd1d;
+        let mut random = |bound: usize| {
+            seed = seed
+                .wrapping_mul(6_364_136_223_846_793_005)
+                .wrapping_add(1_442_695_040_888_963_407);
+            (seed >> 33) as usize % bound
+        };
+
+        for _ in 0..200 {
+            match random(3) {
+                0 | 1 if !tokens.is_empty() && random(2) == 0 => {
+                    let (token_id, owner) = tokens[random(tokens.len())].clone();
+                    let receiver = (owner + 1 + random(owners.len() - 1)) % owners.len();
+                    set_caller(&mut context, owners[owner].clone(), 1);
+                    contract.nft_transfer(owners[receiver].clone(), token_id.clone(), None, None);
+                    tokens.retain(|(id, _)| id != &token_id);
+                    tokens.push((token_id, receiver));
+                }
+                2 if !tokens.is_empty() => {
+                    let (token_id, owner) = tokens.remove(random(tokens.len()));
+                    set_caller(&mut context, owners[owner].clone(), 1);
+                    contract.nft_burn(vec![token_id], None, None);
+                }
+                _ => {
+                    let owner = random(owners.len());
+                    let token_id = next_token_id.to_string();
+                    next_token_id += 1;
+                    mint(
+                        &mut context,
+                        &mut contract,
+                        &token_id,
+                        owners[owner].clone(),
+                    );
+                    tokens.push((token_id, owner));
+                }
+            }
+
+            for (index, owner_id) in owners.iter().enumerate() {
+                let owned = tokens.iter().filter(|(_, owner)| *owner == index).count();
+                assert_eq!(contract.nft_balance(owner_id.clone()), U128(owned as u128));
+                assert_balance_matches_enumeration(&contract, owner_id.clone());
+            }
+            assert_eq!(contract.nft_total_supply(), U128(tokens.len() as u128));
+            contract.assert_counter_invariants();
+        }
+    }
+
+    #[test]
+    fn ownership_transferred_event_json() {
+        let (mut context, mut contract) = setup();
+        set_caller(&mut context, accounts(0), 1);
+        contract.propose_owner(accounts(1));
+        assert!(event::logged_events().is_empty());
+
+        set_caller(&mut context, accounts(1), 1);
+        contract.accept_ownership();
+        assert_eq!(
+            event::logged_events(),
+            [json!({
+                "standard": "contract_ownership",
+                "version": "1.0.0",
+                "event": "ownership_transferred",
+                "data": [{ "new_owner": "bob", "previous_owner": "alice" }]
+            })]
+        );
+    }
+
+    #[test]
+    fn borsh_bytes_round_trip_event_data() {
+        let owner_id = accounts(1);
+        let receiver_id = accounts(2);
+        let event = NearEvent::nft_transfer(vec![NftTransferData::new(
+            &owner_id,
+            &receiver_id,
+            vec!["0", "1"],
+            Some(&receiver_id),
+            Some("gift"),
+        )])
+        .versioned(Nep171Version::V1_2_0);
+
+        let decoded = NearEvent::from_borsh_bytes(&event.to_borsh_bytes()).unwrap();
+
+        assert_eq!(decoded.standard, "nep171");
+        assert_eq!(decoded.version, "1.2.0");
+        assert_eq!(
+            decoded.event_kind,
+            ParsedEventKind::NftTransfer(vec![NftTransferDataOwned {
+                old_owner_id: owner_id.to_string(),
+                new_owner_id: receiver_id.to_string(),
+                token_ids: vec!["0".to_string(), "1".to_string()],
+                authorized_id: Some(receiver_id.to_string()),
+                memo: Some("gift".to_string()),
+            }])
+        );
+        assert_eq!(
+            NearEvent::parse_log(&event.to_json_event_string()).unwrap(),
+            decoded
+        );
+    }
+
+    #[test]
+    fn borsh_bytes_round_trip_approve_event() {
+        let (owner_id, approved_account_id) = (accounts(1), accounts(2));
+        let event = NearEvent::nft_approve(vec![NftApproveData::new(
+            &owner_id,
+            "0",
+            &approved_account_id,
+            7,
+        )]);
+
+        let decoded = NearEvent::from_borsh_bytes(&event.to_borsh_bytes()).unwrap();
+        assert_eq!(decoded, event.to_parsed());
+        assert!(
+            matches!(&decoded.event_kind, ParsedEventKind::NftApprove(data) if data[0].approval_id == 7)
+        );
+    }
+
+    #[test]
+    fn from_borsh_bytes_rejects_truncated_bytes() {
+        let owner_id = accounts(1);
+        let bytes = NearEvent::nft_mint(vec![NftMintData::new(&owner_id, vec!["0"], None)])
+            .to_borsh_bytes();
+
+        assert!(matches!(
+            NearEvent::from_borsh_bytes(&bytes[..bytes.len() - 1]),
+            Err(ParseError::InvalidBorsh(_))
+        ));
+    }
+
+    /// Lists `receiver_id` as the only receiver `nft_transfer_call` may send tokens to.
+    fn allow_receiver(
+        context: &mut VMContextBuilder,
+        contract: &mut Contract,
+        receiver_id: AccountId,
+    ) {
+        set_caller(context, accounts(0), 1);
+        contract.add_allowed_receiver(receiver_id);
+    }
+
+    #[test]
+    fn transfer_call_to_allowed_receiver_succeeds() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        allow_receiver(&mut context, &mut contract, accounts(3));
+
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_transfer_call(accounts(3), "0".to_string(), None, None, "".to_string());
+        assert_eq!(
+            contract.nft_token("0".to_string()).unwrap().owner_id,
+            accounts(3)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Receiver is not allowed")]
+    fn transfer_call_to_unlisted_receiver_panics() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        allow_receiver(&mut context, &mut contract, accounts(3));
+
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_transfer_call(accounts(4), "0".to_string(), None, None, "".to_string());
+    }
+
+    #[test]
+    fn removing_last_allowed_receiver_allows_any_receiver() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        allow_receiver(&mut context, &mut contract, accounts(3));
+        set_caller(&mut context, accounts(0), 1);
+        contract.remove_allowed_receiver(accounts(3));
+
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_transfer_call(accounts(4), "0".to_string(), None, None, "".to_string());
+        assert_eq!(
+            contract.nft_token("0".to_string()).unwrap().owner_id,
+            accounts(4)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "More gas is required")]
+    fn transfer_call_rejects_prepaid_gas_equal_to_threshold() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+
+        context.prepaid_gas(GAS_FOR_NFT_TRANSFER_CALL);
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_transfer_call(accounts(2), "0".to_string(), None, None, "".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "More gas is required")]
+    fn transfer_call_rejects_prepaid_gas_just_below_receiver_minimum() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+
+        context.prepaid_gas(GAS_FOR_NFT_TRANSFER_CALL + MIN_GAS_FOR_RECEIVER - Gas(1));
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_transfer_call(accounts(2), "0".to_string(), None, None, "".to_string());
+    }
+
+    #[test]
+    fn transfer_call_with_receiver_minimum_above_threshold_succeeds() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+
+        context.prepaid_gas(GAS_FOR_NFT_TRANSFER_CALL + MIN_GAS_FOR_RECEIVER);
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_transfer_call(accounts(2), "0".to_string(), None, None, "".to_string());
+
+        assert_eq!(
+            function_call_gas("nft_on_transfer"),
+            vec![MIN_GAS_FOR_RECEIVER]
+        );
+    }
+
+    #[test]
+    fn transfer_with_note_logs_note_as_memo() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        let note = r#"{"provenance":"Olympus Mons expedition"}"#;
+
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_transfer_with_note(accounts(2), "0".to_string(), None, note.to_string());
+
+        assert_eq!(
+            contract.nft_token("0".to_string()).unwrap().owner_id,
+            accounts(2)
+        );
+        assert_eq!(event::last_event()["data"][0]["memo"], note);
+    }
+
+    #[test]
+    #[should_panic(expected = "Note must be at most 512 bytes")]
+    fn transfer_with_oversized_note_panics() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        let note = format!(r#"{{"provenance":"{}"}}"#, "a".repeat(MAX_NOTE_LEN));
+
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_transfer_with_note(accounts(2), "0".to_string(), None, note);
+    }
+
+    #[test]
+    #[should_panic(expected = "Note must be valid JSON")]
+    fn transfer_with_non_json_note_panics() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_transfer_with_note(accounts(2), "0".to_string(), None, "gift".to_string());
+    }
+
+    #[test]
+    fn minters_are_listed_in_order_added() {
+        let (mut context, mut contract) = setup();
+        set_caller(&mut context, accounts(0), 1);
+        contract.add_minter(accounts(2));
+        contract.add_minter(accounts(1));
+
+        assert_eq!(contract.minters(None, None), [accounts(2), accounts(1)]);
+        assert_eq!(contract.minters(Some(U128(1)), Some(1)), [accounts(1)]);
+        assert!(contract.minters(Some(U128(2)), None).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Token transfer is in progress")]
+    fn reentrant_transfer_during_transfer_call_panics() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_transfer_call(accounts(2), "0".to_string(), None, None, "".to_string());
+
+        // The receiver calls back before `nft_resolve_transfer` runs
+        set_caller(&mut context, accounts(2), 1);
+        contract.nft_transfer(accounts(3), "0".to_string(), None, None);
+    }
+
+    #[test]
+    fn resolve_transfer_clears_in_flight_marker() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_transfer_call(accounts(2), "0".to_string(), None, None, "".to_string());
+
+        set_promise_result(&mut context, PromiseResult::Successful(b"false".to_vec()));
+        contract.nft_resolve_transfer(accounts(1), accounts(2), "0".to_string(), None);
+
+        set_caller(&mut context, accounts(2), 1);
+        contract.nft_transfer(accounts(3), "0".to_string(), None, None);
+        assert_eq!(
+            contract.nft_token("0".to_string()).unwrap().owner_id,
+            accounts(3)
+        );
+    }
+
+    #[test]
+    fn owner_clears_stale_in_flight_marker() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_transfer_call(accounts(2), "0".to_string(), None, None, "".to_string());
+
+        // `nft_resolve_transfer` ran out of gas, leaving the marker behind
+        set_caller(&mut context, accounts(0), 1);
+        contract.clear_transfer_in_flight("0".to_string());
+
+        set_caller(&mut context, accounts(2), 1);
+        contract.nft_transfer(accounts(3), "0".to_string(), None, None);
+        assert_eq!(
+            contract.nft_token("0".to_string()).unwrap().owner_id,
+            accounts(3)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Token transfer is not in progress")]
+    fn clearing_missing_in_flight_marker_panics() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+
+        set_caller(&mut context, accounts(0), 1);
+        contract.clear_transfer_in_flight("0".to_string());
+    }
+
+    /// Deploys the contract owned by `accounts(0)` with `default_royalty`.
+    fn setup_with_default_royalty(
+        default_royalty: HashMap<AccountId, u32>,
+    ) -> (VMContextBuilder, Contract) {
+        let context = get_context(accounts(0));
+        testing_env!(context.build());
+        let contract = Contract::new(
+            accounts(0),
+            sample_contract_metadata(),
+            None,
+            None,
+            Some(default_royalty),
+            None,
+            None,
+        );
+        (context, contract)
+    }
+
+    #[test]
+    fn mint_without_royalties_inherits_default_royalty() {
+        let default_royalty = HashMap::from([(accounts(3), 1000)]);
+        let (mut context, mut contract) = setup_with_default_royalty(default_royalty.clone());
+        assert_eq!(contract.default_royalty(), default_royalty);
+        mint(&mut context, &mut contract, "default", accounts(1));
+        set_caller(&mut context, accounts(0), MINT_DEPOSIT);
+        contract.nft_mint(
+            "explicit".to_string(),
+            accounts(1),
+            sample_token_metadata(),
+            Some(HashMap::from([(accounts(2), 500)])),
+            None,
+            None,
+        );
+
+        let payout = contract
+            .nft_payout("default".to_string(), U128(10_000), Some(2))
+            .payout;
+        assert_eq!(payout[&accounts(3)], U128(1_000));
+        assert_eq!(payout[&accounts(1)], U128(9_000));
+        let payout = contract
+            .nft_payout("explicit".to_string(), U128(10_000), Some(2))
+            .payout;
+        assert!(!payout.contains_key(&accounts(3)));
+        assert_eq!(payout[&accounts(2)], U128(500));
+    }
+
+    #[test]
+    #[should_panic(expected = "Royalties can not exceed 10000 basis points")]
+    fn default_royalty_over_10000_basis_points_panics() {
+        setup_with_default_royalty(HashMap::from([(accounts(2), 6000), (accounts(3), 4001)]));
+    }
+
+    /// Runs `call`, which must panic, and asserts that it panicked with the message of `error`.
+    fn assert_panics_with(error: ContractError, call: impl FnOnce()) {
+        let payload = std::panic::catch_unwind(std::panic::AssertUnwindSafe(call)).unwrap_err();
+        let message = payload
+            .downcast_ref::<String>()
+            .map(String::as_str)
+            .or_else(|| payload.downcast_ref::<&str>().copied())
+            .unwrap();
+        assert!(
+            message.contains(error.message()),
+            "{} panicked instead",
+            message
+        );
+    }
+
+    #[test]
+    fn failures_panic_with_contract_error_messages() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+
+        set_caller(&mut context, accounts(1), 1);
+        assert_panics_with(ContractError::TokenNotFound, || {
+            contract.nft_transfer(accounts(2), "1".to_string(), None, None)
+        });
+        set_caller(&mut context, accounts(2), 1);
+        assert_panics_with(ContractError::NotAuthorized, || {
+            contract.nft_transfer(accounts(3), "0".to_string(), None, None)
+        });
+        set_caller(&mut context, accounts(2), MINT_DEPOSIT);
+        assert_panics_with(ContractError::NotAuthorizedMinter, || {
+            contract.nft_mint(
+                "1".to_string(),
+                accounts(2),
+                sample_token_metadata(),
+                None,
+                None,
+                None,
+            );
+        });
+        set_caller(&mut context, accounts(1), 1);
+        assert_panics_with(ContractError::SelfTransfer, || {
+            contract.nft_transfer(accounts(1), "0".to_string(), None, None)
+        });
+        assert_panics_with(ContractError::TokenNotLocked, || {
+            contract.unlock_token("0".to_string())
+        });
+        assert_panics_with(ContractError::NotPendingOwner, || {
+            contract.accept_ownership()
+        });
+        assert_panics_with(ContractError::ContractNotLocked, || {
+            contract.assert_contract_is_locked()
+        });
+
+        set_caller(&mut context, accounts(0), 1);
+        contract.pause();
+        set_caller(&mut context, accounts(1), 1);
+        assert_panics_with(ContractError::Paused, || {
+            contract.nft_transfer(accounts(2), "0".to_string(), None, None)
+        });
+    }
+
+    #[test]
+    fn generic_events_carry_block_context_only_when_enabled() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        approve(&mut context, &mut contract, "0", accounts(1), accounts(2));
+
+        set_caller(&mut context, accounts(2), MINT_DEPOSIT);
+        contract.lock_token("0".to_string());
+        assert!(event::last_event().get("context").is_none());
+
+        set_caller(&mut context, accounts(0), 1);
+        contract.set_event_block_context(true);
+        context.block_index(42).epoch_height(3);
+        set_caller(&mut context, accounts(2), 1);
+        contract.unlock_token("0".to_string());
+        let event = event::last_event();
+        assert_eq!(event["event"], "nft_unlock");
+        assert_eq!(event["context"]["block_height"], 42);
+        assert_eq!(event["context"]["epoch_height"], 3);
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot transfer to current owner")]
+    fn self_transfer_panics() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_transfer(accounts(1), "0".to_string(), None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Cannot transfer to current owner")]
+    fn approved_account_transfer_back_to_owner_panics() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        approve(&mut context, &mut contract, "0", accounts(1), accounts(2));
+
+        set_caller(&mut context, accounts(2), 1);
+        contract.nft_transfer_call(accounts(1), "0".to_string(), None, None, "".to_string());
+    }
+
+    #[test]
+    fn storage_report_sums_after_mints() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        mint(&mut context, &mut contract, "1", accounts(2));
+
+        set_caller(&mut context, accounts(0), MINT_DEPOSIT);
+        let before = contract.nft_storage_report();
+        contract.nft_mint(
+            "2".to_string(),
+            accounts(1),
+            sample_token_metadata(),
+            None,
+            None,
+            None,
+        );
+        let report = contract.nft_storage_report();
+
+        assert_eq!(before.total_tokens, U128(2));
+        assert_eq!(report.total_tokens, U128(3));
+        assert!(report.total_bytes.0 > before.total_bytes.0);
+        assert_eq!(
+            report.total_cost.0,
+            Balance::from(report.total_bytes.0) * env::storage_byte_cost()
+        );
+    }
+
+    #[test]
+    fn batch_transfer_keeps_distinct_memos_in_separate_entries() {
+        let (mut context, mut contract) = setup();
+        for token_id in ["0", "1", "2"] {
+            mint(&mut context, &mut contract, token_id, accounts(1));
+        }
+
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_batch_transfer(vec![
+            (
+                accounts(2),
+                "0".to_string(),
+                None,
+                Some("first".to_string()),
+            ),
+            (
+                accounts(2),
+                "1".to_string(),
+                None,
+                Some("second".to_string()),
+            ),
+            (
+                accounts(2),
+                "2".to_string(),
+                None,
+                Some("first".to_string()),
+            ),
+        ]);
+
+        let event = event::last_event();
+        let data = event["data"].as_array().unwrap();
+        assert_eq!(data.len(), 2);
+        assert_eq!(data[0]["memo"], "first");
+        assert_eq!(data[0]["token_ids"], json!(["0", "2"]));
+        assert_eq!(data[1]["memo"], "second");
+        assert_eq!(data[1]["token_ids"], json!(["1"]));
+    }
+
+    #[test]
+    fn mint_sale_records_price_in_memo_only_when_provided() {
+        let (mut context, mut contract) = setup();
+        set_caller(&mut context, accounts(0), MINT_DEPOSIT);
+        contract.nft_mint_sale(
+            "0".to_string(),
+            accounts(1),
+            sample_token_metadata(),
+            Some(U128(5_000_000_000_000_000_000_000_000)),
+        );
+        let memo = event::last_event()["data"][0]["memo"].clone();
+        assert_eq!(
+            near_sdk::serde_json::from_str::<Value>(memo.as_str().unwrap()).unwrap(),
+            json!({ "price": "5000000000000000000000000" })
+        );
+
+        set_caller(&mut context, accounts(0), MINT_DEPOSIT);
+        contract.nft_mint_sale("1".to_string(), accounts(1), sample_token_metadata(), None);
+        assert!(event::last_event()["data"][0].get("memo").is_none());
+    }
+
+    /// Number of mint events logged by the last call.
+    fn mint_event_count() -> usize {
+        event::logged_events()
+            .iter()
+            .filter(|event| event["event"] == "nft_mint")
+            .count()
+    }
+
+    #[test]
+    fn mint_idempotent_replay_returns_same_token_without_event() {
+        let (mut context, mut contract) = setup();
+        set_caller(&mut context, accounts(0), MINT_DEPOSIT);
+        let token = contract.nft_mint_idempotent(
+            "request-1".to_string(),
+            "0".to_string(),
+            accounts(1),
+            sample_token_metadata(),
+        );
+        assert_eq!(mint_event_count(), 1);
+
+        set_caller(&mut context, accounts(0), MINT_DEPOSIT);
+        let replayed = contract.nft_mint_idempotent(
+            "request-1".to_string(),
+            "1".to_string(),
+            accounts(2),
+            sample_token_metadata(),
+        );
+
+        assert_eq!(replayed.token_id, token.token_id);
+        assert_eq!(replayed.owner_id, accounts(1));
+        assert_eq!(contract.nft_total_supply(), U128(1));
+        assert_eq!(mint_event_count(), 0);
+        assert_eq!(transferred_to(&accounts(0)), MINT_DEPOSIT);
+    }
+
+    /// Deploys the contract owned by `accounts(0)` requiring a memo on every transfer.
+    fn setup_requiring_memo() -> (VMContextBuilder, Contract) {
+        let context = get_context(accounts(0));
+        testing_env!(context.build());
+        let contract = Contract::new(
+            accounts(0),
+            sample_contract_metadata(),
+            None,
+            None,
+            None,
+            Some(true),
+            None,
+        );
+        (context, contract)
+    }
+
+    #[test]
+    #[should_panic(expected = "Memo is required")]
+    fn required_memo_missing_panics() {
+        let (mut context, mut contract) = setup_requiring_memo();
+        mint(&mut context, &mut contract, "0", accounts(1));
+
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_transfer(accounts(2), "0".to_string(), None, None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Memo is required")]
+    fn required_memo_missing_in_transfer_call_panics() {
+        let (mut context, mut contract) = setup_requiring_memo();
+        mint(&mut context, &mut contract, "0", accounts(1));
+
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_transfer_call(accounts(2), "0".to_string(), None, None, "".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "Memo is required")]
+    fn required_memo_missing_in_batch_transfer_panics() {
+        let (mut context, mut contract) = setup_requiring_memo();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        mint(&mut context, &mut contract, "1", accounts(1));
+
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_batch_transfer(vec![
+            (
+                accounts(2),
+                "0".to_string(),
+                None,
+                Some("invoice 7".to_string()),
+            ),
+            (accounts(2), "1".to_string(), None, None),
+        ]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Memo is required")]
+    fn required_memo_missing_in_transfer_payout_panics() {
+        let (mut context, mut contract) = setup_requiring_memo();
+        mint(&mut context, &mut contract, "0", accounts(1));
+
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_transfer_payout(accounts(2), "0".to_string(), None, None, U128(100), None);
+    }
+
+    #[test]
+    fn required_memo_present_transfers() {
+        let (mut context, mut contract) = setup_requiring_memo();
+        mint(&mut context, &mut contract, "0", accounts(1));
+
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_transfer(
+            accounts(2),
+            "0".to_string(),
+            None,
+            Some("invoice 7".to_string()),
+        );
+        assert_eq!(
+            contract.nft_token("0".to_string()).unwrap().owner_id,
+            accounts(2)
+        );
+        assert_eq!(event::last_event()["data"][0]["memo"], "invoice 7");
+    }
+
+    /// Deploys the contract owned by `accounts(0)` reporting `supply_milestones`.
+    fn setup_with_supply_milestones(supply_milestones: Vec<u64>) -> (VMContextBuilder, Contract) {
+        let context = get_context(accounts(0));
+        testing_env!(context.build());
+        let contract = Contract::new(
+            accounts(0),
+            sample_contract_metadata(),
+            None,
+            None,
+            None,
+            None,
+            Some(supply_milestones),
+        );
+        (context, contract)
+    }
+
+    /// Names of the events logged by the last call, storage refunds aside.
+    fn logged_event_names() -> Vec<String> {
+        event::logged_events()
+            .iter()
+            .map(|event| event["event"].as_str().unwrap().to_string())
+            .filter(|event| event != "storage_refund")
+            .collect()
+    }
+
+    #[test]
+    fn supply_milestone_is_emitted_once_after_crossing_mint() {
+        let (mut context, mut contract) = setup_with_supply_milestones(vec![2, 10]);
+        mint(&mut context, &mut contract, "0", accounts(1));
+        assert_eq!(logged_event_names(), ["nft_mint"]);
+
+        mint(&mut context, &mut contract, "1", accounts(1));
+        assert_eq!(logged_event_names(), ["nft_mint", "supply_milestone"]);
+        let event = event::last_event();
+        assert_eq!(event["standard"], "nft_supply");
+        assert_eq!(event["data"][0]["milestone"], "2");
+        assert_eq!(event["data"][0]["total_supply"], "2");
+
+        // Burning and minting back to the milestone doesn't report it again
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_burn(vec!["1".to_string()], None, None);
+        mint(&mut context, &mut contract, "2", accounts(1));
+        assert_eq!(logged_event_names(), ["nft_mint"]);
+    }
+
+    #[test]
+    fn batch_mint_emits_milestones_after_its_mint_event() {
+        let (mut context, mut contract) = setup_with_supply_milestones(vec![1, 3, 5]);
+        set_caller(&mut context, accounts(0), MINT_DEPOSIT);
+        contract.nft_batch_mint(
+            vec!["0".to_string(), "1".to_string(), "2".to_string()],
+            accounts(1),
+            vec![sample_token_metadata(); 3],
+        );
+
+        assert_eq!(
+            logged_event_names(),
+            ["nft_mint", "supply_milestone", "supply_milestone"]
+        );
+        let milestones: Vec<_> = event::logged_events()
+            .iter()
+            .filter(|event| event["event"] == "supply_milestone")
+            .map(|event| event["data"][0]["milestone"].clone())
+            .collect();
+        assert_eq!(milestones, [json!("1"), json!("3")]);
+    }
+
+    #[test]
+    fn capture_events_keeps_contract_events_off_the_logs() {
+        let (mut context, mut contract) = setup();
+        set_caller(&mut context, accounts(0), MINT_DEPOSIT);
+        let sink = event::capture_events(|| {
+            contract.nft_mint(
+                "0".to_string(),
+                accounts(1),
+                sample_token_metadata(),
+                None,
+                None,
+                None,
+            );
+        });
+
+        assert!(event::logged_events().is_empty());
+        let events = sink.events();
+        assert_eq!(events.len(), 2);
+        assert!(matches!(
+            events[0].event_kind,
+            ParsedEventKind::StorageRefund(_)
+        ));
+        match &events[1].event_kind {
+            ParsedEventKind::NftMint(data) => {
+                assert_eq!(data[0].owner_id, accounts(1).as_str());
+                assert_eq!(data[0].token_ids, ["0"]);
+            }
+            event_kind => panic!("Expected a mint event, got {:?}", event_kind),
+        }
+    }
+
+    #[test]
+    fn captured_events_skip_logs_they_cannot_parse() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        approve(&mut context, &mut contract, "0", accounts(1), accounts(2));
+
+        set_caller(&mut context, accounts(2), MINT_DEPOSIT);
+        let sink = event::capture_events(|| contract.lock_token("0".to_string()));
+
+        // The lock event isn't a NEP-171 or NEP-178 event, unlike the refund of the deposit
+        assert_eq!(sink.logs.len(), 2);
+        let events = sink.events();
+        assert_eq!(events.len(), 1);
+        assert!(matches!(
+            events[0].event_kind,
+            ParsedEventKind::StorageRefund(_)
+        ));
+    }
+
+    #[test]
+    fn burning_increments_burned_and_decrements_supply() {
+        let (mut context, mut contract) = setup();
+        for token_id in ["0", "1", "2"] {
+            mint(&mut context, &mut contract, token_id, accounts(1));
+        }
+        assert_eq!(contract.nft_total_burned(), U128(0));
+
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_burn(vec!["0".to_string(), "1".to_string()], None, None);
+        assert_eq!(contract.nft_total_burned(), U128(2));
+        assert_eq!(contract.nft_total_supply(), U128(1));
+
+        mint(&mut context, &mut contract, "3", accounts(1));
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_burn(vec!["3".to_string()], None, None);
+        assert_eq!(contract.nft_total_burned(), U128(3));
+        assert_eq!(contract.nft_total_supply(), U128(1));
+    }
+
+    /// Makes the next approval of `token_id` get `next_approval_id`.
+    fn set_next_approval_id(contract: &mut Contract, token_id: &str, next_approval_id: u64) {
+        contract
+            .tokens
+            .next_approval_id_by_id
+            .as_mut()
+            .unwrap()
+            .insert(&token_id.to_string(), &next_approval_id);
+    }
+
+    #[test]
+    fn last_approval_id_is_below_u64_max() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        set_next_approval_id(&mut contract, "0", u64::MAX - 1);
+
+        assert_eq!(
+            approve(&mut context, &mut contract, "0", accounts(1), accounts(2)),
+            u64::MAX - 1
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Approval ids of the token are exhausted")]
+    fn approval_id_overflow_panics() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        set_next_approval_id(&mut contract, "0", u64::MAX - 1);
+        approve(&mut context, &mut contract, "0", accounts(1), accounts(2));
+
+        approve(&mut context, &mut contract, "0", accounts(1), accounts(3));
+    }
+
+    #[test]
+    fn mint_refunds_deposit_beyond_storage_cost() {
+        let (mut context, mut contract) = setup();
+        set_caller(&mut context, accounts(0), MINT_DEPOSIT);
+        let initial_storage_usage = env::storage_usage();
+        contract.nft_mint(
+            "0".to_string(),
+            accounts(1),
+            sample_token_metadata(),
+            None,
+            None,
+            None,
+        );
+        let storage_cost =
+            Balance::from(env::storage_usage() - initial_storage_usage) * env::storage_byte_cost();
+
+        assert_eq!(transferred_to(&accounts(0)), MINT_DEPOSIT - storage_cost);
+        assert_eq!(
+            event::logged_events()[0]["data"][0]["amount"],
+            (MINT_DEPOSIT - storage_cost).to_string()
+        );
+    }
+
+    #[test]
+    fn refund_deposit_keeps_only_a_single_yocto_excess() {
+        let (mut context, contract) = setup();
+        let storage_cost = 10 * env::storage_byte_cost();
+
+        set_caller(&mut context, accounts(0), storage_cost + 1);
+        contract.refund_deposit(10);
+        assert_eq!(transferred_to(&accounts(0)), 0);
+
+        set_caller(&mut context, accounts(0), storage_cost + 2);
+        contract.refund_deposit(10);
+        assert_eq!(transferred_to(&accounts(0)), 2);
+    }
+
+    #[test]
+    fn mint_logs_no_event_while_events_are_off() {
+        let (mut context, mut contract) = setup();
+        set_caller(&mut context, accounts(0), 1);
+        contract.set_emit_events(false);
+
+        mint(&mut context, &mut contract, "0", accounts(1));
+        assert!(event::logged_events().is_empty());
+        assert_eq!(contract.nft_total_supply(), U128(1));
+
+        set_caller(&mut context, accounts(0), 1);
+        contract.set_emit_events(true);
+        mint(&mut context, &mut contract, "1", accounts(1));
+        assert_eq!(event::last_event()["event"], "nft_mint");
+    }
+
+    /// Names of the functions called by the receipts the last call created, in creation order.
+    fn called_functions() -> Vec<String> {
+        near_sdk::test_utils::get_created_receipts()
+            .into_iter()
+            .flat_map(|receipt| receipt.actions)
+            .filter_map(|action| match action {
+                near_sdk::mock::VmAction::FunctionCall { function_name, .. } => Some(function_name),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn transfer_call_logs_transfer_before_receiver_and_reversal_after() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_transfer_call(accounts(2), "0".to_string(), None, None, "".to_string());
+        // The transfer is logged by the call that schedules the receiver and the resolver
+        let events = event::logged_events();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["event"], "nft_transfer");
+        assert_eq!(events[0]["data"][0]["new_owner_id"], accounts(2).as_str());
+        assert_eq!(
+            called_functions(),
+            ["nft_on_transfer", "nft_resolve_transfer"]
+        );
+
+        // The receiver asks for the token back, so the resolver logs the reversal
+        set_promise_result(&mut context, PromiseResult::Successful(b"true".to_vec()));
+        contract.nft_resolve_transfer(accounts(1), accounts(2), "0".to_string(), None);
+        let events = event::logged_events();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["event"], "nft_transfer");
+        assert_eq!(events[0]["data"][0]["old_owner_id"], accounts(2).as_str());
+        assert_eq!(events[0]["data"][0]["new_owner_id"], accounts(1).as_str());
+    }
+
+    #[test]
+    fn token_flags_report_soulbound_lock_and_freeze() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        assert_eq!(
+            contract.nft_token_flags("0".to_string()),
+            Some(TokenFlags {
+                soulbound: false,
+                locked_by: None,
+                frozen: false,
+            })
+        );
+
+        mint_soulbound(&mut context, &mut contract, "1");
+        approve(&mut context, &mut contract, "1", accounts(1), accounts(2));
+        set_caller(&mut context, accounts(2), MINT_DEPOSIT);
+        contract.lock_token("1".to_string());
+        set_caller(&mut context, accounts(0), 1);
+        contract.freeze_metadata();
+
+        assert_eq!(
+            contract.nft_token_flags("1".to_string()),
+            Some(TokenFlags {
+                soulbound: true,
+                locked_by: Some(accounts(2)),
+                frozen: true,
+            })
+        );
+        assert!(contract.nft_token_flags("missing".to_string()).is_none());
+    }
+
+    #[test]
+    fn royalty_update_changes_payout_and_emits_event() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+
+        set_caller(&mut context, accounts(0), MINT_DEPOSIT);
+        contract.nft_update_royalty("0".to_string(), HashMap::from([(accounts(3), 2000)]));
+
+        let event = event::last_event();
+        assert_eq!(event["event"], "royalty_updated");
+        assert_eq!(event["data"][0]["token_id"], "0");
+        assert_eq!(event["data"][0]["royalty"][accounts(3).as_str()], 2000);
+        let payout = contract
+            .nft_payout("0".to_string(), U128(10_000), Some(2))
+            .payout;
+        assert_eq!(payout[&accounts(3)], U128(2_000));
+        assert_eq!(payout[&accounts(1)], U128(8_000));
+    }
+
+    #[test]
+    #[should_panic(expected = "Royalties are frozen")]
+    fn royalty_update_after_freeze_panics() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        set_caller(&mut context, accounts(0), 1);
+        contract.freeze_royalties();
+
+        set_caller(&mut context, accounts(0), MINT_DEPOSIT);
+        contract.nft_update_royalty("0".to_string(), HashMap::from([(accounts(3), 2000)]));
+    }
+
+    #[test]
+    #[should_panic(expected = "Unauthorized")]
+    fn token_owner_cannot_update_royalty_unless_allowed() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+
+        set_caller(&mut context, accounts(1), MINT_DEPOSIT);
+        contract.nft_update_royalty("0".to_string(), HashMap::from([(accounts(3), 2000)]));
+    }
+
+    #[test]
+    fn tokens_by_mint_time_returns_window() {
+        let (mut context, mut contract) = setup();
+        for (token_id, minted_at) in [("a", 100), ("b", 200), ("c", 300), ("d", 400)] {
+            context.block_timestamp(minted_at);
+            mint(&mut context, &mut contract, token_id, accounts(1));
+        }
+        let token_ids = |tokens: Vec<Token>| {
+            tokens
+                .into_iter()
+                .map(|token| token.token_id)
+                .collect::<Vec<_>>()
+        };
+
+        let window = contract.nft_tokens_by_mint_time(U64(150), U64(400), None);
+        assert_eq!(token_ids(window), ["b", "c"]);
+        let window = contract.nft_tokens_by_mint_time(U64(100), U64(401), Some(2));
+        assert_eq!(token_ids(window), ["a", "b"]);
+
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_burn(vec!["c".to_string()], None, None);
+        let window = contract.nft_tokens_by_mint_time(U64(150), U64(500), None);
+        assert_eq!(token_ids(window), ["b", "d"]);
+    }
+
+    #[test]
+    fn mint_with_too_little_deposit_reports_shortfall() {
+        let (mut context, mut contract) = setup();
+        set_caller(&mut context, accounts(0), 1);
+        let initial_storage_usage = env::storage_usage();
+        let payload = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            contract.nft_mint(
+                "0".to_string(),
+                accounts(1),
+                sample_token_metadata(),
+                None,
+                None,
+                None,
+            )
+        }))
+        .unwrap_err();
+
+        let required_cost =
+            Balance::from(env::storage_usage() - initial_storage_usage) * env::storage_byte_cost();
+        assert!(payload.downcast_ref::<String>().unwrap().contains(&format!(
+            "Must attach {} yoctoNEAR to cover storage, {} more than attached",
+            required_cost,
+            required_cost - 1
+        )));
+    }
+
+    #[test]
+    fn lenient_transfer_data_accepts_canonical_and_legacy_names() {
+        let canonical = NftTransferDataOwned::from_lenient(json!({
+            "old_owner_id": "bob",
+            "new_owner_id": "charlie",
+            "token_ids": ["0"],
+            "authorized_id": "danny",
+            "memo": "gift",
+        }))
+        .unwrap();
+        let legacy = NftTransferDataOwned::from_lenient(json!({
+            "sender_id": "bob",
+            "receiver_id": "charlie",
+            "token_ids": ["0"],
+            "authorized_id": "danny",
+            "memo": "gift",
+        }))
+        .unwrap();
+
+        assert_eq!(canonical, legacy);
+        assert_eq!(canonical.old_owner_id, "bob");
+        assert_eq!(canonical.new_owner_id, "charlie");
+        assert!(matches!(
+            NftTransferDataOwned::from_lenient(json!({ "sender_id": "bob", "token_ids": ["0"] })),
+            Err(ParseError::InvalidJson(_))
+        ));
+    }
+
+    #[test]
+    fn base64_event_data_decodes_to_original_data() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        let plain = event::last_event();
+
+        set_caller(&mut context, accounts(0), 1);
+        contract.set_event_data_base64(true);
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_transfer(accounts(2), "0".to_string(), None, Some("gift".to_string()));
+
+        let encoded = event::last_event();
+        assert_eq!(encoded["standard"], "nep171");
+        assert_eq!(encoded["event"], "nft_transfer");
+        assert!(encoded["data"].is_string());
+        let decoded = event::decode_base64_data(&format!("EVENT_JSON:{}", encoded)).unwrap();
+        assert_eq!(
+            decoded["data"],
+            json!([{
+                "old_owner_id": "bob",
+                "new_owner_id": "charlie",
+                "token_ids": ["0"],
+                "memo": "gift",
+            }])
+        );
+        assert!(plain["data"].is_array());
+    }
+
+    #[test]
+    fn atomic_swap_emits_a_transfer_event_for_each_side() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        mint(&mut context, &mut contract, "1", accounts(2));
+        set_caller(&mut context, accounts(2), MINT_DEPOSIT);
+        contract.nft_offer_swap("1".to_string(), "0".to_string());
+
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_atomic_swap("0".to_string(), "1".to_string(), accounts(2));
+
+        let events = event::logged_events();
+        assert_eq!(events.len(), 2);
+        assert_eq!(
+            events[0]["data"],
+            json!([{ "old_owner_id": "bob", "new_owner_id": "charlie", "token_ids": ["0"] }])
+        );
+        assert_eq!(
+            events[1]["data"],
+            json!([{
+                "authorized_id": "bob",
+                "old_owner_id": "charlie",
+                "new_owner_id": "bob",
+                "token_ids": ["1"],
+            }])
+        );
+        assert_eq!(
+            contract.nft_token("0".to_string()).unwrap().owner_id,
+            accounts(2)
+        );
+        assert_eq!(
+            contract.nft_token("1".to_string()).unwrap().owner_id,
+            accounts(1)
+        );
+        assert_eq!(contract.nft_swap_offer("1".to_string()), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "Counterparty hasn't offered their token for this token")]
+    fn atomic_swap_requires_an_offer_from_the_counterparty() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        mint(&mut context, &mut contract, "1", accounts(2));
+        mint(&mut context, &mut contract, "2", accounts(1));
+        // Approving the contract isn't consent to a swap
+        approve(&mut context, &mut contract, "1", accounts(2), contract_id());
+        set_caller(&mut context, accounts(2), MINT_DEPOSIT);
+        contract.nft_offer_swap("1".to_string(), "2".to_string());
+
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_atomic_swap("0".to_string(), "1".to_string(), accounts(2));
+    }
+
+    #[test]
+    fn swap_offer_storage_is_charged_and_refunded_once_the_offer_is_dropped() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        mint(&mut context, &mut contract, "1", accounts(2));
+        mint(&mut context, &mut contract, "2", accounts(2));
+
+        set_caller(&mut context, accounts(2), MINT_DEPOSIT);
+        let initial_storage_usage = env::storage_usage();
+        contract.nft_offer_swap("1".to_string(), "0".to_string());
+        let storage_cost =
+            env::storage_byte_cost() * Balance::from(env::storage_usage() - initial_storage_usage);
+        assert!(storage_cost > 0);
+        assert_eq!(transferred_to(&accounts(2)), MINT_DEPOSIT - storage_cost);
+
+        set_caller(&mut context, accounts(2), 1);
+        contract.nft_cancel_swap_offer("1".to_string());
+        assert_eq!(transferred_to(&accounts(2)), storage_cost);
+
+        // A swap consumes the offer of the counterparty and refunds it too
+        set_caller(&mut context, accounts(2), MINT_DEPOSIT);
+        contract.nft_offer_swap("2".to_string(), "0".to_string());
+        set_caller(&mut context, accounts(1), 1);
+        contract.nft_atomic_swap("0".to_string(), "2".to_string(), accounts(2));
+        assert_eq!(transferred_to(&accounts(2)), storage_cost);
+    }
+
+    #[test]
+    fn owner_summary_counts_tokens_and_previews_their_ids() {
+        let (mut context, mut contract) = setup();
+        for token_id in 0..12 {
+            mint(
+                &mut context,
+                &mut contract,
+                &token_id.to_string(),
+                accounts(1),
+            );
+        }
+        mint(&mut context, &mut contract, "12", accounts(2));
+
+        let summary = contract.nft_owner_summary(accounts(1), None);
+        assert_eq!(summary.count, U128(12));
+        assert_eq!(summary.token_ids.len(), 10);
+        assert!(summary.token_ids.iter().all(|token_id| token_id != "12"));
+
+        let summary = contract.nft_owner_summary(accounts(1), Some(3));
+        assert_eq!(summary.count, U128(12));
+        assert_eq!(summary.token_ids.len(), 3);
+
+        assert_eq!(
+            contract.nft_owner_summary(accounts(3), None),
+            OwnerSummary {
+                count: U128(0),
+                token_ids: vec![],
+            }
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "testing")]
+    fn event_schema_samples_parse_back_into_their_event_kinds() {
+        let (_, contract) = setup();
+
+        let samples = contract.event_schema_samples();
+        assert_eq!(samples.len(), 9);
+        let mut nep171_events = Vec::new();
+        for sample in &samples {
+            let event: serde_json::Value =
+                serde_json::from_str(sample.strip_prefix("EVENT_JSON:").unwrap()).unwrap();
+            let kind = json!({ "event": event["event"], "data": event["data"] }).to_string();
+            // Each kind serializes back to the same JSON it was parsed from
+            let reserialized = match event["standard"].as_str().unwrap() {
+                "nep171" => {
+                    let kind: Nep171EventKind = serde_json::from_str(&kind).unwrap();
+                    nep171_events.push(event["event"].clone());
+                    serde_json::to_string(&kind).unwrap()
+                }
+                "nep178" => {
+                    let kind: event::Nep178EventKind = serde_json::from_str(&kind).unwrap();
+                    serde_json::to_string(&kind).unwrap()
+                }
+                "storage" => {
+                    let kind: event::StorageEventKind = serde_json::from_str(&kind).unwrap();
+                    serde_json::to_string(&kind).unwrap()
+                }
+                standard => panic!("Unexpected standard {}", standard),
+            };
+            assert_eq!(
+                serde_json::from_str::<serde_json::Value>(&reserialized).unwrap(),
+                serde_json::from_str::<serde_json::Value>(&kind).unwrap()
+            );
+        }
+        assert_eq!(
+            nep171_events,
+            vec![
+                "nft_mint",
+                "nft_transfer",
+                "nft_burn",
+                "nft_metadata_update",
+                "contract_metadata_update"
+            ]
+        );
+    }
+
+    #[test]
+    fn burn_rules_for_locked_and_soulbound_tokens() {
+        // (soulbound, locked, burned by the owner rather than an approved account, error)
+        let cases = [
+            (false, false, true, None),
+            (false, false, false, None),
+            (false, true, true, Some(ContractError::TokenLocked)),
+            (false, true, false, Some(ContractError::TokenLocked)),
+            (true, false, true, None),
+            (true, false, false, Some(ContractError::NotSoulboundOwner)),
+            (true, true, true, Some(ContractError::TokenLocked)),
+            (true, true, false, Some(ContractError::TokenLocked)),
+        ];
+        let (mut context, mut contract) = setup();
+        for (index, &(soulbound, locked, by_owner, error)) in cases.iter().enumerate() {
+            let token_id = index.to_string();
+            if soulbound {
+                mint_soulbound(&mut context, &mut contract, &token_id);
+            } else {
+                mint(&mut context, &mut contract, &token_id, accounts(1));
+            }
+            approve(
+                &mut context,
+                &mut contract,
+                &token_id,
+                accounts(1),
+                accounts(2),
+            );
+            if locked {
+                set_caller(&mut context, accounts(2), MINT_DEPOSIT);
+                contract.lock_token(token_id.clone());
+            }
+
+            let burner = if by_owner { accounts(1) } else { accounts(2) };
+            set_caller(&mut context, burner, 1);
+            let mut burn = || contract.nft_burn(vec![token_id.clone()], None, None);
+            match error {
+                Some(error) => assert_panics_with(error, burn),
+                None => {
+                    burn();
+                    assert!(contract.nft_token(token_id.clone()).is_none());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn historical_mint_emits_events_for_existing_tokens() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        mint(&mut context, &mut contract, "1", accounts(2));
+        mint(&mut context, &mut contract, "2", accounts(1));
+        set_caller(&mut context, accounts(0), 1);
+        contract.set_migration_mode(true);
+
+        set_caller(&mut context, accounts(0), 1);
+        contract.emit_historical_mint(vec!["0".to_string(), "1".to_string(), "2".to_string()]);
+
+        let events = event::logged_events();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["event"], "nft_mint");
+        assert_eq!(
+            events[0]["data"],
+            json!([
+                { "owner_id": accounts(1), "token_ids": ["0", "2"] },
+                { "owner_id": accounts(2), "token_ids": ["1"] },
+            ])
+        );
+        assert_eq!(contract.nft_total_supply(), U128(3));
+    }
+
+    #[test]
+    #[should_panic(expected = "Migration mode is not set")]
+    fn historical_mint_requires_migration_mode() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+
+        set_caller(&mut context, accounts(0), 1);
+        contract.emit_historical_mint(vec!["0".to_string()]);
+    }
+
+    #[test]
+    fn invalid_token_ids_are_rejected_on_every_path() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        let invalid_ids = [
+            (String::new(), ContractError::TokenIdEmpty),
+            ("a".repeat(257), ContractError::TokenIdTooLong),
+            ("a\nb".to_string(), ContractError::TokenIdInvalidChars),
+        ];
+
+        for (token_id, error) in invalid_ids.iter() {
+            set_caller(&mut context, accounts(0), MINT_DEPOSIT);
+            assert_panics_with(*error, || {
+                contract.nft_mint(
+                    token_id.clone(),
+                    accounts(1),
+                    sample_token_metadata(),
+                    None,
+                    None,
+                    None,
+                );
+            });
+            assert_panics_with(*error, || {
+                contract.nft_mint_idempotent(
+                    "request".to_string(),
+                    token_id.clone(),
+                    accounts(1),
+                    sample_token_metadata(),
+                );
+            });
+            assert_panics_with(*error, || {
+                contract.nft_mint_and_approve(
+                    token_id.clone(),
+                    accounts(1),
+                    sample_token_metadata(),
+                    accounts(2),
+                );
+            });
+            assert_panics_with(*error, || {
+                contract.nft_batch_mint(
+                    vec![token_id.clone()],
+                    accounts(1),
+                    vec![sample_token_metadata()],
+                );
+            });
+            assert_panics_with(*error, || {
+                contract.nft_batch_mint_to(vec![(
+                    token_id.clone(),
+                    accounts(1),
+                    sample_token_metadata(),
+                )]);
+            });
+
+            set_caller(&mut context, accounts(1), 1);
+            assert_panics_with(*error, || {
+                contract.nft_transfer(accounts(2), token_id.clone(), None, None);
+            });
+            assert_panics_with(*error, || {
+                contract.nft_burn(vec![token_id.clone()], None, None);
+            });
+            set_caller(&mut context, accounts(1), MINT_DEPOSIT);
+            assert_panics_with(*error, || {
+                contract.nft_approve(token_id.clone(), accounts(2), None);
+            });
+        }
+        assert_eq!(contract.nft_total_supply(), U128(1));
+    }
+
+    #[test]
+    fn transfer_from_by_operator_logs_operator_as_authorized_id() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        let approval_id = approve(&mut context, &mut contract, "0", accounts(1), accounts(2));
+
+        set_caller(&mut context, accounts(2), 1);
+        contract.nft_transfer_from(
+            accounts(1),
+            accounts(3),
+            "0".to_string(),
+            approval_id,
+            Some("sale".to_string()),
+        );
+
+        assert_eq!(
+            contract.nft_token("0".to_string()).unwrap().owner_id,
+            accounts(3)
+        );
+        let events = event::logged_events();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["event"], "nft_transfer");
+        assert_eq!(
+            events[0]["data"],
+            json!([{
+                "authorized_id": accounts(2),
+                "old_owner_id": accounts(1),
+                "new_owner_id": accounts(3),
+                "token_ids": ["0"],
+                "memo": "sale",
+            }])
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "The actual approval_id is different from the given approval_id")]
+    fn transfer_from_rejects_stale_approval_id() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        let approval_id = approve(&mut context, &mut contract, "0", accounts(1), accounts(2));
+
+        set_caller(&mut context, accounts(2), 1);
+        contract.nft_transfer_from(
+            accounts(1),
+            accounts(3),
+            "0".to_string(),
+            approval_id + 1,
+            None,
+        );
+    }
+
+    #[test]
+    fn tokens_metadata_returns_minted_metadata_by_page() {
+        let (mut context, mut contract) = setup();
+        let metadata = |index: usize| TokenMetadata {
+            title: Some(format!("Token {}", index)),
+            ..sample_token_metadata()
+        };
+        for index in 0..5 {
+            set_caller(&mut context, accounts(0), MINT_DEPOSIT);
+            contract.nft_mint(
+                index.to_string(),
+                accounts(1),
+                metadata(index),
+                None,
+                None,
+                None,
+            );
+        }
+
+        let all = contract.nft_tokens_metadata(None, None);
+        assert_eq!(all.len(), 5);
+        for (index, (token_id, token_metadata)) in all.iter().enumerate() {
+            assert_eq!(token_id, &index.to_string());
+            assert_eq!(
+                serde_json::to_value(token_metadata).unwrap(),
+                serde_json::to_value(metadata(index)).unwrap()
+            );
+        }
+
+        let page = contract.nft_tokens_metadata(Some(U128(1)), Some(2));
+        let token_ids: Vec<_> = page.iter().map(|(token_id, _)| token_id.as_str()).collect();
+        assert_eq!(token_ids, vec!["1", "2"]);
+        assert_eq!(page[1].1.title.as_deref(), Some("Token 2"));
+        assert!(contract.nft_tokens_metadata(Some(U128(5)), None).is_empty());
+    }
+
+    #[test]
+    fn lock_storage_is_charged_and_refunded_once_the_lock_is_released() {
+        let (mut context, mut contract) = setup();
+        mint(&mut context, &mut contract, "0", accounts(1));
+        mint(&mut context, &mut contract, "1", accounts(1));
+        approve(&mut context, &mut contract, "0", accounts(1), accounts(2));
+        let approval_id = approve(&mut context, &mut contract, "1", accounts(1), accounts(2));
+
+        set_caller(&mut context, accounts(2), MINT_DEPOSIT);
+        let initial_storage_usage = env::storage_usage();
+        contract.lock_token("0".to_string());
+        let storage_cost =
+            env::storage_byte_cost() * Balance::from(env::storage_usage() - initial_storage_usage);
+        assert!(storage_cost > 0);
+        assert_eq!(transferred_to(&accounts(2)), MINT_DEPOSIT - storage_cost);
+
+        set_caller(&mut context, accounts(2), 1);
+        contract.unlock_token("0".to_string());
+        assert_eq!(transferred_to(&accounts(2)), storage_cost);
+
+        // A transfer by the lock holder uses the lock up and refunds it too
+        set_caller(&mut context, accounts(2), MINT_DEPOSIT);
+        contract.lock_token("1".to_string());
+        set_caller(&mut context, accounts(2), 1);
+        contract.nft_transfer(accounts(3), "1".to_string(), Some(approval_id), None);
+        assert_eq!(transferred_to(&accounts(2)), storage_cost);
     }
 }
@@ -0,0 +1,74 @@
+use crate::error::ContractError;
+use crate::event::GenericEvent;
+use crate::Contract;
+use near_contract_standards::non_fungible_token::TokenId;
+use near_sdk::serde_json::json;
+use near_sdk::{assert_one_yocto, env, near_bindgen, require, AccountId, FunctionError};
+
+/// Locks aren't part of any NEP, so their events are reported under a standard of their own.
+pub(crate) const LOCK_EVENT_STANDARD: &str = "nft_lock";
+pub(crate) const LOCK_EVENT_VERSION: &str = "1.0.0";
+
+impl Contract {
+    fn emit_lock_event(&self, event: &str, token_id: &TokenId, locker: &AccountId) {
+        self.emit_generic_event(GenericEvent::new(
+            LOCK_EVENT_STANDARD,
+            LOCK_EVENT_VERSION,
+            event,
+            json!([{ "token_id": token_id, "locker": locker }]),
+        ));
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Locks `token_id` so that only the caller can transfer it until it is unlocked, and emits
+    /// an `nft_lock` event. Only an approved account of the token may call it, attaching a
+    /// deposit for the storage of the lock, which is refunded once the lock is released or used
+    /// up by a transfer.
+    #[payable]
+    pub fn lock_token(&mut self, token_id: TokenId) {
+        let predecessor_id = env::predecessor_account_id();
+        require!(
+            self.current_approval_id(&token_id, &predecessor_id)
+                .is_some(),
+            ContractError::NotApprovedToLock.message()
+        );
+        self.assert_approval_not_expired(&token_id, &predecessor_id);
+        require!(
+            !self.locks.contains_key(&token_id),
+            ContractError::TokenAlreadyLocked.message()
+        );
+        let initial_storage_usage = env::storage_usage();
+        self.locks.insert(&token_id, &predecessor_id);
+
+        // Charge the storage of the lock and return the rest of the deposit
+        self.refund_deposit(env::storage_usage() - initial_storage_usage);
+
+        self.emit_lock_event("nft_lock", &token_id, &predecessor_id);
+    }
+
+    /// Releases the lock on `token_id`, refunding its storage, and emits an `nft_unlock` event.
+    /// Only the lock holder may call it.
+    #[payable]
+    pub fn unlock_token(&mut self, token_id: TokenId) {
+        assert_one_yocto();
+        let locked_by = self
+            .locks
+            .get(&token_id)
+            .unwrap_or_else(|| ContractError::TokenNotLocked.panic());
+        require!(
+            locked_by == env::predecessor_account_id(),
+            ContractError::NotLockHolder.message()
+        );
+        let initial_storage_usage = env::storage_usage();
+        self.locks.remove(&token_id);
+        crate::refund_storage(&locked_by, initial_storage_usage - env::storage_usage());
+
+        self.emit_lock_event("nft_unlock", &token_id, &locked_by);
+    }
+
+    pub fn nft_locked_by(&self, token_id: TokenId) -> Option<AccountId> {
+        self.locks.get(&token_id)
+    }
+}
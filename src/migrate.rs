@@ -0,0 +1,57 @@
+use crate::Contract;
+use near_contract_standards::non_fungible_token::metadata::NFTContractMetadata;
+use near_contract_standards::non_fungible_token::NonFungibleToken;
+use near_sdk::borsh::{self, BorshDeserialize};
+use near_sdk::collections::LazyOption;
+use near_sdk::{env, near_bindgen};
+
+/// State layout of the contract before royalties, minters, pausing and the other
+/// extensions were added.
+#[derive(BorshDeserialize)]
+struct OldContract {
+    tokens: NonFungibleToken,
+    metadata: LazyOption<NFTContractMetadata>,
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Upgrades the state of a collection deployed with the original layout. The tokens and
+    /// contract metadata are kept, and the fields added since start from their defaults:
+    /// - `royalties`, `soulbound`, `locks` and `approval_expiries` start empty,
+    /// - `minters` starts empty, so only the owner can mint,
+    /// - `paused` and `pause_mints_and_burns` start as `false`,
+    /// - `pending_owner` starts as `None`,
+    /// - `balances` starts empty, so `nft_balance` counts the tokens of existing owners from
+    ///   the enumeration until they next receive or send a token,
+    /// - `event_version` starts as 1.0.0,
+    /// - `gas_for_on_transfer` starts as `None`, keeping the default gas split,
+    /// - `tokens_minted` starts at the current supply, as tokens burned before the migration
+    ///   can't be counted,
+    /// - `contract_is_locked` starts as `false` until the deployer sets it again,
+    /// - `max_supply` starts as `None`, leaving the supply uncapped,
+    /// - `operator_approvals` starts empty,
+    /// - `metadata_frozen` starts as `false`,
+    /// - `allowed_receivers` starts empty, so `nft_transfer_call` accepts any receiver,
+    /// - `transfers_in_flight` starts empty,
+    /// - `default_royalty` starts empty, so tokens only get the royalties they're minted with,
+    /// - `event_block_context` starts as `false`,
+    /// - `mint_requests` and `mint_request_ids` start empty,
+    /// - `require_memo` starts as `false`,
+    /// - `supply_milestones` starts empty,
+    /// - `tokens_burned` starts at 0,
+    /// - `emit_events` starts as `true`,
+    /// - `token_owner_updates_royalty` and `royalties_frozen` start as `false`,
+    /// - `mint_times` and `mint_log` start empty, so only tokens minted after the migration
+    ///   are enumerated by mint time,
+    /// - `event_data_base64` starts as `false`,
+    /// - `swap_offers` starts empty,
+    /// - `migration_mode` starts as `false`, until the owner sets it to emit the mint events of
+    ///   the existing tokens.
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let old_state: OldContract =
+            env::state_read().unwrap_or_else(|| env::panic_str("Failed to read old state"));
+        Self::from_parts(old_state.tokens, old_state.metadata)
+    }
+}
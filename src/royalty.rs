@@ -0,0 +1,190 @@
+use crate::error::ContractError;
+use crate::event::{GenericEvent, NearEvent, NftTransferData};
+use crate::Contract;
+use near_contract_standards::non_fungible_token::TokenId;
+use near_sdk::json_types::U128;
+use near_sdk::serde_json::json;
+use near_sdk::{assert_one_yocto, env, near_bindgen, require, AccountId, FunctionError};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Royalties are expressed in basis points, 10 000 being the whole sale price.
+const ROYALTY_TOTAL_BASIS_POINTS: u32 = 10_000;
+
+/// NEP-199 defines no events, so royalty updates are reported under a standard of their own.
+pub(crate) const ROYALTY_EVENT_STANDARD: &str = "nft_royalty";
+pub(crate) const ROYALTY_EVENT_VERSION: &str = "1.0.0";
+
+/// NEP-199 payout: how the sale price of a token is split between accounts.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Payout {
+    pub payout: HashMap<AccountId, U128>,
+}
+
+pub(crate) fn assert_valid_royalty(royalty: &HashMap<AccountId, u32>) {
+    let total: u32 = royalty.values().sum();
+    require!(
+        total <= ROYALTY_TOTAL_BASIS_POINTS,
+        ContractError::RoyaltyTooHigh.message()
+    );
+}
+
+/// Splits `balance` according to `royalty`, assigning the remainder to `owner_id`.
+fn royalty_to_payout(
+    royalty: &HashMap<AccountId, u32>,
+    owner_id: AccountId,
+    balance: u128,
+    max_len_payout: Option<u32>,
+) -> Payout {
+    let payout_len = royalty.len() + usize::from(!royalty.contains_key(&owner_id));
+    if let Some(max_len_payout) = max_len_payout {
+        require!(
+            payout_len <= max_len_payout as usize,
+            ContractError::TooManyPayoutReceivers.message()
+        );
+    }
+
+    let mut payout: HashMap<AccountId, U128> = HashMap::with_capacity(payout_len);
+    let mut paid = 0;
+    for (account_id, basis_points) in royalty {
+        let amount = balance * u128::from(*basis_points) / u128::from(ROYALTY_TOTAL_BASIS_POINTS);
+        paid += amount;
+        payout.insert(account_id.clone(), U128(amount));
+    }
+    let owner_amount = payout.entry(owner_id).or_insert(U128(0));
+    owner_amount.0 += balance - paid;
+    Payout { payout }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Returns how `balance` would be split if `token_id` was sold for it. The owner receives
+    /// whatever the royalties leave, so the amounts always sum to `balance`.
+    pub fn nft_payout(
+        &self,
+        token_id: TokenId,
+        balance: U128,
+        max_len_payout: Option<u32>,
+    ) -> Payout {
+        let owner_id = self
+            .tokens
+            .owner_by_id
+            .get(&token_id)
+            .unwrap_or_else(|| ContractError::TokenNotFound.panic());
+        let royalty = self.royalties.get(&token_id).unwrap_or_default();
+        royalty_to_payout(&royalty, owner_id, balance.0, max_len_payout)
+    }
+
+    /// Replaces the perpetual royalties of `token_id` and emits a `royalty_updated` event. Only
+    /// the contract owner may call it, or the token owner if `set_token_owner_updates_royalty`
+    /// allowed it, until `freeze_royalties` is called. Storage the new royalties add is paid
+    /// from the attached deposit, and storage they release is refunded.
+    #[payable]
+    pub fn nft_update_royalty(&mut self, token_id: TokenId, royalty: HashMap<AccountId, u32>) {
+        require!(
+            env::attached_deposit() > 0,
+            ContractError::DepositRequired.message()
+        );
+        require!(
+            !self.royalties_frozen,
+            ContractError::RoyaltiesFrozen.message()
+        );
+        let owner_id = self
+            .tokens
+            .owner_by_id
+            .get(&token_id)
+            .unwrap_or_else(|| ContractError::TokenNotFound.panic());
+        let predecessor_id = env::predecessor_account_id();
+        require!(
+            predecessor_id == self.tokens.owner_id
+                || (self.token_owner_updates_royalty && predecessor_id == owner_id),
+            ContractError::NotAuthorized.message()
+        );
+        assert_valid_royalty(&royalty);
+
+        let initial_storage_usage = env::storage_usage();
+        if royalty.is_empty() {
+            self.royalties.remove(&token_id);
+        } else {
+            self.royalties.insert(&token_id, &royalty);
+        }
+        let storage_usage = env::storage_usage();
+        self.refund_deposit(storage_usage.saturating_sub(initial_storage_usage));
+        crate::refund_storage(
+            &predecessor_id,
+            initial_storage_usage.saturating_sub(storage_usage),
+        );
+
+        // Create a GenericEvent, as NEP-199 has no events
+        self.emit_generic_event(GenericEvent::new(
+            ROYALTY_EVENT_STANDARD,
+            ROYALTY_EVENT_VERSION,
+            "royalty_updated",
+            json!([{ "token_id": token_id, "royalty": royalty }]),
+        ));
+    }
+
+    /// Sets whether token owners may update the royalties of their tokens on top of the
+    /// contract owner. Only the contract owner may call it.
+    #[payable]
+    pub fn set_token_owner_updates_royalty(&mut self, token_owner_updates_royalty: bool) {
+        assert_one_yocto();
+        self.assert_owner();
+        self.token_owner_updates_royalty = token_owner_updates_royalty;
+    }
+
+    /// Permanently prevents updates of token royalties. Only the contract owner may call it,
+    /// and it can't be undone.
+    #[payable]
+    pub fn freeze_royalties(&mut self) {
+        assert_one_yocto();
+        self.assert_owner();
+        self.royalties_frozen = true;
+    }
+
+    pub fn are_royalties_frozen(&self) -> bool {
+        self.royalties_frozen
+    }
+
+    /// Royalties applied to tokens minted without perpetual royalties of their own.
+    pub fn default_royalty(&self) -> HashMap<AccountId, u32> {
+        self.default_royalty.clone()
+    }
+
+    /// Transfers `token_id` to `receiver_id` and returns how the marketplace should split
+    /// the `balance` it received for the sale.
+    #[payable]
+    pub fn nft_transfer_payout(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: TokenId,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+        balance: U128,
+        max_len_payout: Option<u32>,
+    ) -> Payout {
+        assert_one_yocto();
+        self.assert_not_paused();
+        self.assert_memo_if_required(&memo);
+        let sender_id = env::predecessor_account_id();
+        let (old_owner_id, _) = self.internal_transfer(
+            &sender_id,
+            &receiver_id,
+            &token_id,
+            approval_id,
+            memo.clone(),
+        );
+
+        // Create a NearEvent
+        self.emit_event(NearEvent::nft_transfer(vec![NftTransferData::new(
+            &old_owner_id,
+            &receiver_id,
+            vec![&token_id],
+            Some(&sender_id).filter(|sender_id| *sender_id != &old_owner_id),
+            memo.as_deref(),
+        )]));
+
+        let royalty = self.royalties.get(&token_id).unwrap_or_default();
+        royalty_to_payout(&royalty, old_owner_id, balance.0, max_len_payout)
+    }
+}
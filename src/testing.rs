@@ -0,0 +1,59 @@
+use crate::event::{
+    NearEvent, NftApproveData, NftBurnData, NftContractMetadataUpdateData, NftMetadataUpdateData,
+    NftMintData, NftRevokeAllData, NftRevokeData, NftTransferData, StorageRefundData,
+};
+use crate::Contract;
+use near_contract_standards::non_fungible_token::core::NonFungibleTokenCore;
+use near_contract_standards::non_fungible_token::{Token, TokenId};
+use near_sdk::{near_bindgen, AccountId};
+
+/// Methods only compiled with the `testing` feature, to keep integration test assertions
+/// descriptive without growing the production contract.
+#[near_bindgen]
+impl Contract {
+    /// Like `nft_token`, but reports a missing token as an error naming it.
+    pub fn nft_token_checked(&self, token_id: TokenId) -> Result<Token, String> {
+        self.nft_token(token_id.clone())
+            .ok_or_else(|| format!("Token {} not found", token_id))
+    }
+
+    /// Returns an `EVENT_JSON` log of each event the contract emits under a NEP, with
+    /// placeholder data and NEP-171 events under the configured version, e.g. for indexers to
+    /// snapshot.
+    pub fn event_schema_samples(&self) -> Vec<String> {
+        let owner_id: AccountId = "alice.near".parse().unwrap();
+        let receiver_id: AccountId = "bob.near".parse().unwrap();
+        let token_id = "token-1";
+        let memo = Some("memo");
+        vec![
+            NearEvent::nft_mint(vec![NftMintData::new(&owner_id, vec![token_id], memo)]),
+            NearEvent::nft_transfer(vec![NftTransferData::new(
+                &owner_id,
+                &receiver_id,
+                vec![token_id],
+                Some(&receiver_id),
+                memo,
+            )]),
+            NearEvent::nft_burn(vec![NftBurnData::new(
+                &owner_id,
+                vec![token_id],
+                Some(&receiver_id),
+                memo,
+            )]),
+            NearEvent::nft_metadata_update(vec![NftMetadataUpdateData::new(vec![token_id], memo)]),
+            NearEvent::nft_contract_metadata_update(vec![NftContractMetadataUpdateData::new(memo)]),
+            NearEvent::nft_approve(vec![NftApproveData::new(
+                &owner_id,
+                token_id,
+                &receiver_id,
+                1,
+            )]),
+            NearEvent::nft_revoke(vec![NftRevokeData::new(&owner_id, token_id, &receiver_id)]),
+            NearEvent::nft_revoke_all(vec![NftRevokeAllData::new(&owner_id, token_id)]),
+            NearEvent::storage_refund(vec![StorageRefundData::new(&owner_id, 1)]),
+        ]
+        .into_iter()
+        .map(|event| event.versioned(self.event_version).to_json_event_string())
+        .collect()
+    }
+}